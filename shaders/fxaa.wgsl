@@ -0,0 +1,72 @@
+// Fast approximate anti-aliasing, after Timothy Lottes' FXAA: blurs along edges found from
+// luma contrast between neighbouring pixels.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // Fullscreen triangle covering clip space, with (0, 0) uv at the top left.
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+// Smallest amount the edge direction is scaled down by, so flat areas aren't blurred
+const REDUCE_MIN: f32 = 1.0 / 128.0;
+// How much brighter areas reduce the edge direction
+const REDUCE_MUL: f32 = 1.0 / 8.0;
+// Furthest along an edge samples are taken, in pixels
+const SPAN_MAX: f32 = 8.0;
+
+// Perceived brightness; sRGB targets sample as linear, so approximate gamma encoding first.
+fn luma(color: vec3<f32>) -> f32 {
+    return dot(sqrt(color), vec3<f32>(0.299, 0.587, 0.114));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+
+    let center = textureSample(t_source, s_source, in.uv);
+    let luma_nw = luma(textureSample(t_source, s_source, in.uv + vec2<f32>(-1.0, -1.0) * texel).rgb);
+    let luma_ne = luma(textureSample(t_source, s_source, in.uv + vec2<f32>(1.0, -1.0) * texel).rgb);
+    let luma_sw = luma(textureSample(t_source, s_source, in.uv + vec2<f32>(-1.0, 1.0) * texel).rgb);
+    let luma_se = luma(textureSample(t_source, s_source, in.uv + vec2<f32>(1.0, 1.0) * texel).rgb);
+    let luma_m = luma(center.rgb);
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Perpendicular to the luma gradient, i.e. along the edge.
+    var dir = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let dir_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    let rcp_dir_min = 1.0 / (min(abs(dir.x), abs(dir.y)) + dir_reduce);
+    dir = clamp(dir * rcp_dir_min, vec2<f32>(-SPAN_MAX), vec2<f32>(SPAN_MAX)) * texel;
+
+    let rgb_a = 0.5 * (
+        textureSample(t_source, s_source, in.uv + dir * (1.0 / 3.0 - 0.5)).rgb +
+        textureSample(t_source, s_source, in.uv + dir * (2.0 / 3.0 - 0.5)).rgb
+    );
+    let rgb_b = rgb_a * 0.5 + 0.25 * (
+        textureSample(t_source, s_source, in.uv + dir * -0.5).rgb +
+        textureSample(t_source, s_source, in.uv + dir * 0.5).rgb
+    );
+
+    // The wider blur overshot the local contrast, so it crossed another edge.
+    let luma_b = luma(rgb_b);
+    let rgb = select(rgb_b, rgb_a, luma_b < luma_min || luma_b > luma_max);
+    return vec4<f32>(rgb, center.a);
+}
@@ -0,0 +1,51 @@
+struct Frame {
+    view_proj: mat4x4<f32>,
+    resolution: vec2<f32>,
+    time: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> frame: Frame;
+
+struct InstanceInput {
+    @location(0) position: vec2<f32>,
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32, in: InstanceInput) -> VertexOutput {
+    // Two triangles covering the sprite, from its bottom left corner.
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[in_vertex_index];
+
+    var out: VertexOutput;
+    out.position = frame.view_proj * vec4<f32>(in.position + corner * in.size, 0.0, 1.0);
+    // Textures have (0, 0) at the top left.
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    out.color = in.color;
+    return out;
+}
+
+@group(1) @binding(0)
+var t_sprite: texture_2d<f32>;
+@group(1) @binding(1)
+var s_sprite: sampler;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_sprite, s_sprite, in.uv) * in.color;
+}
\ No newline at end of file
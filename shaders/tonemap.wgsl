@@ -0,0 +1,59 @@
+// Maps the HDR scene into the displayable range. The engine prepends `OPERATOR` (0 for Reinhard,
+// 1 for ACES) and `ENCODE_SRGB`, set when the output format doesn't gamma encode by itself.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // Fullscreen triangle covering clip space, with (0, 0) uv at the top left.
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(t_source, s_source, in.uv);
+    let color = max(hdr.rgb, vec3<f32>(0.0));
+
+    var mapped: vec3<f32>;
+    if OPERATOR == 0u {
+        mapped = reinhard(color);
+    } else {
+        mapped = aces(color);
+    }
+    if ENCODE_SRGB {
+        mapped = linear_to_srgb(mapped);
+    }
+    return vec4<f32>(mapped, clamp(hdr.a, 0.0, 1.0));
+}
@@ -0,0 +1,5 @@
+// Paired with another shader's vertex stage to draw its geometry as a flat-colored wireframe.
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
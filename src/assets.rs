@@ -0,0 +1,11 @@
+use std::path::PathBuf;
+use std::sync::mpsc::Sender;
+
+/// Sent whenever an asset is reloaded from disk, see [`crate::engine::Engine::drain_asset_events`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetReloaded {
+    pub path: PathBuf,
+}
+
+/// Where asset reloads are reported to
+pub type AssetEventSender = Sender<AssetReloaded>;
@@ -0,0 +1,306 @@
+use std::time::Duration;
+
+use glam::{Mat4, Vec2, Vec3};
+use winit::event::MouseButton;
+
+use crate::input::InputState;
+
+/// Narrowest allowed field of view in degrees
+pub const MIN_FOV: f32 = 10.0;
+/// Widest allowed field of view in degrees
+pub const MAX_FOV: f32 = 120.0;
+/// How quickly the field of view approaches its target; higher is snappier
+const FOV_LERP_RATE: f32 = 12.0;
+/// Furthest the camera can look up or down in radians, short of straight up where the view
+/// would flip
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+/// Radians the orbit camera turns per unit of raw mouse movement while dragging
+const ORBIT_SENSITIVITY: f32 = 0.005;
+/// Fraction of the distance the orbit camera zooms in by per scroll line
+const ORBIT_ZOOM_STEP: f32 = 0.1;
+
+/// Which camera the frame uniforms are built from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveCamera {
+    #[default]
+    Camera2D,
+    Camera3D,
+    Orbit,
+}
+
+/// A perspective camera turned by yaw and pitch.
+#[derive(Debug)]
+pub struct Camera3D {
+    pub position: Vec3,
+    /// Rotation around the y axis in radians; `0` looks down -z
+    yaw: f32,
+    /// Rotation above the horizon in radians
+    pitch: f32,
+    /// Current vertical field of view in degrees
+    fov: f32,
+    /// Field of view `fov` is being interpolated towards
+    target_fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Camera3D {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 0.0, 2.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            fov: 70.0,
+            target_fov: 70.0,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl Camera3D {
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Turns the camera by the given radians, keeping the pitch short of straight up or down.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Unit vector the camera looks along
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch)
+    }
+
+    /// Unit vector to the right of the camera, parallel to the ground
+    pub fn right(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        Vec3::new(cos_yaw, 0.0, sin_yaw)
+    }
+
+    /// World to clip space transform for a surface with the given width/height `aspect` ratio
+    pub fn view_projection(&self, aspect: f32) -> Mat4 {
+        let projection = Mat4::perspective_rh(self.fov.to_radians(), aspect, self.near, self.far);
+        projection * Mat4::look_to_rh(self.position, self.forward(), Vec3::Y)
+    }
+
+    pub fn fov(&self) -> f32 {
+        self.fov
+    }
+
+    pub fn target_fov(&self) -> f32 {
+        self.target_fov
+    }
+
+    /// Sets the field of view to smoothly interpolate towards, clamped to [`MIN_FOV`, `MAX_FOV`]
+    pub fn set_target_fov(&mut self, fov: f32) {
+        self.target_fov = fov.clamp(MIN_FOV, MAX_FOV);
+    }
+
+    /// Steps the field of view towards its target; framerate independent.
+    pub fn update(&mut self, dt: Duration) {
+        let t = 1.0 - (-FOV_LERP_RATE * dt.as_secs_f32()).exp();
+        self.fov += (self.target_fov - self.fov) * t;
+    }
+}
+
+/// A perspective camera circling `target`, for inspecting models. Drag with the left mouse
+/// button to rotate and scroll to zoom.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCamera {
+    /// Point the camera looks at and rotates around
+    pub target: Vec3,
+    /// Distance from the target, kept within `min_distance..=max_distance`
+    distance: f32,
+    /// Rotation around the y axis in radians; `0` is on the +z side of the target
+    yaw: f32,
+    /// Height above the target's horizon in radians
+    pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Vertical field of view in degrees
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            target: Vec3::ZERO,
+            distance: 2.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            min_distance: 0.1,
+            max_distance: 100.0,
+            fov: 70.0,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl OrbitCamera {
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Sets the distance from the target, clamped to `min_distance..=max_distance`.
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.clamp(self.min_distance, self.max_distance);
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
+    /// Circles the target by the given radians, stopping short of directly above or below it.
+    pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Rotates by this frame's left button drag and zooms by its scroll.
+    pub fn handle_input(&mut self, input: &InputState) {
+        if input.is_button_held(MouseButton::Left) {
+            let (dx, dy) = input.mouse_motion();
+            self.rotate(
+                -dx as f32 * ORBIT_SENSITIVITY,
+                dy as f32 * ORBIT_SENSITIVITY,
+            );
+        }
+        // Zooming by a fraction of the distance feels the same close up and far away.
+        self.set_distance(self.distance * (1.0 - ORBIT_ZOOM_STEP).powf(input.scroll_delta()));
+    }
+
+    /// World position of the camera
+    pub fn position(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        self.target + Vec3::new(sin_yaw * cos_pitch, sin_pitch, cos_yaw * cos_pitch) * self.distance
+    }
+
+    /// World to view space transform
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position(), self.target, Vec3::Y)
+    }
+
+    /// World to clip space transform for a surface with the given width/height `aspect` ratio
+    pub fn view_projection(&self, aspect: f32) -> Mat4 {
+        let projection = Mat4::perspective_rh(self.fov.to_radians(), aspect, self.near, self.far);
+        projection * self.view()
+    }
+}
+
+/// An orthographic camera looking down the z axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera2D {
+    /// World position at the center of the screen
+    pub position: Vec2,
+    /// Magnification; at `1.0` the screen spans `-1..1` world units vertically
+    pub zoom: f32,
+}
+
+impl Default for Camera2D {
+    fn default() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera2D {
+    /// Moves the camera by `offset` world units.
+    pub fn pan(&mut self, offset: Vec2) {
+        self.position += offset;
+    }
+
+    /// Multiplies the zoom by `factor`, keeping it positive.
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).max(f32::EPSILON);
+    }
+
+    /// World to clip space transform for a surface with the given width/height `aspect` ratio
+    pub fn view_projection(&self, aspect: f32) -> Mat4 {
+        let half_height = 1.0 / self.zoom;
+        let half_width = half_height * aspect;
+        let projection = Mat4::orthographic_rh(
+            -half_width,
+            half_width,
+            -half_height,
+            half_height,
+            -1.0,
+            1.0,
+        );
+        projection * Mat4::from_translation(-self.position.extend(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec4;
+
+    use super::*;
+
+    /// Projects `point` to normalized device coordinates.
+    fn project(camera: &Camera3D, aspect: f32, point: Vec3) -> Vec3 {
+        let clip = camera.view_projection(aspect) * point.extend(1.0);
+        clip.truncate() / clip.w
+    }
+
+    #[test]
+    fn projects_world_points_to_clip_space() {
+        let camera = Camera3D {
+            position: Vec3::new(0.0, 0.0, 2.0),
+            fov: 90.0,
+            ..Default::default()
+        };
+
+        // One unit in front and one unit to the right lands on the right edge of a 90 degree
+        // square frustum.
+        let clip = camera.view_projection(1.0) * Vec4::new(1.0, 0.0, 1.0, 1.0);
+        assert!((clip.w - 1.0).abs() < 1e-5);
+        let ndc = project(&camera, 1.0, Vec3::new(1.0, 0.0, 1.0));
+        assert!((ndc.x - 1.0).abs() < 1e-5, "{ndc}");
+        assert!(ndc.y.abs() < 1e-5, "{ndc}");
+        // Depth goes from 0 at the near plane to 1 at the far plane.
+        let depth = camera.far / (camera.near - camera.far) * (camera.near - 1.0);
+        assert!((ndc.z - depth).abs() < 1e-5, "{ndc}");
+
+        // A wider aspect ratio narrows the horizontal extent.
+        let ndc = project(&camera, 2.0, Vec3::new(1.0, 0.0, 1.0));
+        assert!((ndc.x - 0.5).abs() < 1e-5, "{ndc}");
+    }
+
+    #[test]
+    fn yaw_turns_the_view() {
+        let mut camera = Camera3D::default();
+        camera.rotate(std::f32::consts::FRAC_PI_2, 0.0);
+
+        // Facing +x, a point straight ahead is at the center of the screen.
+        let ndc = project(&camera, 1.0, camera.position + Vec3::X * 5.0);
+        assert!(ndc.x.abs() < 1e-5 && ndc.y.abs() < 1e-5, "{ndc}");
+    }
+
+    #[test]
+    fn pitch_is_clamped() {
+        let mut camera = Camera3D::default();
+        camera.rotate(0.0, 10.0);
+        assert_eq!(camera.pitch(), MAX_PITCH);
+        camera.rotate(0.0, -20.0);
+        assert_eq!(camera.pitch(), -MAX_PITCH);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use crate::input::TextEvent;
+use crate::util::callback::Callback;
+
+/// Most lines kept in the scrollback
+const MAX_LINES: usize = 200;
+/// Lines of scrollback shown above the prompt
+const VISIBLE_LINES: usize = 20;
+
+/// Scrollback shared with [`ConsoleLogger`], which may log from any thread
+static LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Called with the whitespace-separated arguments after the command's name
+pub type CommandFn = dyn FnMut(&[&str]);
+pub type Command = Callback<CommandFn>;
+
+/// Appends `line` to the console's scrollback.
+pub fn print(line: impl Into<String>) {
+    let mut lines = LINES.lock().unwrap();
+    if lines.len() == MAX_LINES {
+        lines.pop_front();
+    }
+    lines.push_back(line.into());
+}
+
+/// Clears the console's scrollback.
+pub fn clear() {
+    LINES.lock().unwrap().clear();
+}
+
+/// Forwards records to `inner`, also printing those it lets through to the console.
+pub struct ConsoleLogger<L> {
+    inner: L,
+}
+
+impl<L: log::Log> ConsoleLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+}
+
+impl<L: log::Log> log::Log for ConsoleLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.inner.enabled(record.metadata()) {
+            print(format!("[{}] {}", record.level(), record.args()));
+        }
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// A drop-down command prompt over the scene, showing log output and the results of commands.
+#[derive(Debug, Default)]
+pub struct Console {
+    open: bool,
+    /// The command being typed
+    line: String,
+    commands: HashMap<String, Command>,
+}
+
+impl Console {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    pub fn close(&mut self) {
+        self.open = false;
+    }
+
+    /// Adds a command run as `name arg0 arg1 ...`, replacing any with the same name.
+    pub fn register(&mut self, name: impl Into<String>, command: Command) {
+        self.commands.insert(name.into(), command);
+    }
+
+    /// Names of the registered commands, sorted
+    pub fn commands(&self) -> Vec<&str> {
+        let mut names: Vec<_> = self.commands.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Edits the prompt with `events`, returning the line if Enter submitted one.
+    pub(crate) fn on_text_events(&mut self, events: &[TextEvent]) -> Option<String> {
+        let mut submitted = None;
+        for event in events {
+            match event {
+                // The toggle key types a backtick too.
+                TextEvent::Text(text) => self.line.extend(text.chars().filter(|&c| c != '`')),
+                TextEvent::Backspace => {
+                    self.line.pop();
+                }
+                TextEvent::Delete => {}
+                TextEvent::Enter => submitted = Some(std::mem::take(&mut self.line)),
+            }
+        }
+        submitted
+    }
+
+    /// Runs the registered command `name`, returning whether there was one.
+    pub(crate) fn run(&mut self, name: &str, args: &[&str]) -> bool {
+        match self.commands.get_mut(name) {
+            Some(command) => {
+                command(args);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The visible scrollback followed by the prompt
+    pub(crate) fn text(&self) -> String {
+        let lines = LINES.lock().unwrap();
+        let mut text = String::new();
+        for line in lines.iter().skip(lines.len().saturating_sub(VISIBLE_LINES)) {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text.push_str("> ");
+        text.push_str(&self.line);
+        text.push('_');
+        text
+    }
+}
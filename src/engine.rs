@@ -1,154 +1,1805 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use log::*;
+#[cfg(not(target_arch = "wasm32"))]
 use pollster::FutureExt as _;
-use winit::event::ElementState;
+use web_time::Instant;
 use winit::event_loop::ControlFlow;
 
+use crate::assets::{AssetEventSender, AssetReloaded};
+use crate::camera::{ActiveCamera, Camera2D, Camera3D, OrbitCamera};
+use crate::console::{self, Console};
+use crate::graphics::draw::DrawContext;
+use crate::graphics::sprite::SpriteBatch;
 use crate::graphics::GraphicsContext;
-use crate::settings::GraphicsSettings;
-use crate::util::error::DrawError;
+use crate::input::{self, InputState};
+use crate::settings::{GraphicsSettings, WindowSettings};
+#[cfg(feature = "egui")]
+use crate::ui::{GuiCallback, Ui};
+use crate::util::callback::Callback;
+use crate::util::error::{DrawError, EngineError};
 use crate::util::performance_stats::PerformanceStats;
 
+/// Degrees the field of view changes per zoom key press or scroll line
+const FOV_STEP: f32 = 5.0;
+/// World units per second the 3D camera flies at
+const CAMERA_SPEED: f32 = 3.0;
+/// Radians the 3D camera turns per unit of raw mouse movement, roughly a pixel
+const MOUSE_SENSITIVITY: f32 = 0.003;
+/// Minimum time between present timing diagnostics logs
+const TIMING_LOG_INTERVAL: Duration = Duration::from_secs(1);
+/// Minimum time between warnings about frames going over budget
+const BUDGET_WARNING_INTERVAL: Duration = Duration::from_secs(1);
+/// Radians per second the 3D camera turns at with a gamepad's right stick fully deflected
+#[cfg(feature = "gamepad")]
+const STICK_TURN_SPEED: f32 = 2.5;
+/// Longest time a single frame may advance the simulation by, so a frame stalled by e.g. a
+/// breakpoint or window drag doesn't trigger a long catch-up
+const MAX_DELTA_TIME: Duration = Duration::from_millis(250);
+/// Framerate videos are recorded at
+#[cfg(feature = "record")]
+pub const RECORD_FRAMERATE: f64 = 60.0;
+/// Framerate caps cycled through at runtime; `None` is uncapped with vsync
+const FRAMERATE_CAPS: [Option<f64>; 4] = [Some(30.0), Some(60.0), Some(120.0), None];
+
+#[cfg(target_arch = "wasm32")]
+type PendingGraphicsContext =
+    std::rc::Rc<std::cell::RefCell<Option<Result<GraphicsContext<'static>, EngineError>>>>;
+
+/// Asked whether to exit when a window is closed or the quit key pressed; `false` keeps running
+pub type CloseHandler = Callback<dyn FnMut() -> bool>;
+/// Records custom draws into the main render pass each frame
+pub type RenderCallback = Callback<dyn FnMut(&mut DrawContext)>;
+/// Runs application logic once per fixed update step
+pub type UpdateCallback = Callback<dyn FnMut(&mut UpdateContext, Duration)>;
+
+/// What the update callback can see and change each step.
+pub struct UpdateContext<'a> {
+    pub input: &'a InputState,
+    pub camera: &'a mut Camera3D,
+    pub camera_2d: &'a mut Camera2D,
+    pub orbit_camera: &'a mut OrbitCamera,
+    pub active_camera: &'a mut ActiveCamera,
+    /// Changes to the clear color and framerate are applied before the frame is drawn.
+    pub settings: &'a mut GraphicsSettings,
+    exit_requested: &'a mut bool,
+}
+
+impl UpdateContext<'_> {
+    /// Exits after this frame's updates, unless the close handler vetoes it.
+    pub fn request_exit(&mut self) {
+        *self.exit_requested = true;
+    }
+}
+
 #[derive(Debug)]
 pub struct Engine<'a> {
     window_attributes: winit::window::WindowAttributes,
+    window_settings: WindowSettings,
     window: Option<Arc<winit::window::Window>>,
     has_focus: bool,
+    /// Whether the window stays hidden until its first frame has been drawn
+    deferred_show: bool,
+    /// Whether the window is fully hidden, e.g. covered or minimized, in which case nothing is drawn
+    occluded: bool,
+    /// Size to restore when leaving fullscreen, `Some` while fullscreen
+    windowed_size: Option<winit::dpi::PhysicalSize<u32>>,
+    /// Physical pixels per logical pixel of the monitor the window is on
+    scale_factor: f64,
+    /// Latest inner size of the window, ahead of `surface_size` while a resize is pending
+    size: winit::dpi::PhysicalSize<u32>,
+    /// Size the surface is currently configured for
+    surface_size: winit::dpi::PhysicalSize<u32>,
+    /// Latest size from a burst of resizes and when it arrived, applied on the next redraw once
+    /// debounced
+    pending_resize: Option<(winit::dpi::PhysicalSize<u32>, Instant)>,
+    /// Windows besides the main one, each with its own surface and scene
+    windows: HashMap<winit::window::WindowId, (Arc<winit::window::Window>, GraphicsContext<'a>)>,
+    /// Windows to open once the event loop is running
+    pending_windows: Vec<winit::window::WindowAttributes>,
 
     graphics_context: Option<GraphicsContext<'a>>,
     graphics_settings: GraphicsSettings,
+    /// Filled in once the graphics context being created in the background is ready
+    #[cfg(target_arch = "wasm32")]
+    pending_graphics_context: PendingGraphicsContext,
 
     next_frame_time: Instant,
+    last_update: Instant,
+    /// Time since the previous frame, at most [`MAX_DELTA_TIME`]
+    delta_time: Duration,
+    /// Whether updates are skipped, while drawing carries on
+    paused: bool,
+    /// Whether the pause came from losing focus, and so ends on regaining it
+    paused_by_focus: bool,
+    /// Time not yet simulated, always less than one update step after updating
+    update_accumulator: Duration,
+    /// How far between the last and next update the current frame is, in `[0, 1)`
+    interpolation_alpha: f64,
+    last_timing_log: Instant,
+    /// When a frame was last reported for going over the frametime budget
+    last_budget_warning: Option<Instant>,
+
+    camera: Camera3D,
+    camera_2d: Camera2D,
+    orbit_camera: OrbitCamera,
+    active_camera: ActiveCamera,
+    input: InputState,
+    /// Toggled with the backtick key
+    console: Console,
+    /// Asset reloads are sent here by whatever reloads them, and drained by application code
+    asset_events: (AssetEventSender, Receiver<AssetReloaded>),
+    /// The video being recorded, if any
+    #[cfg(feature = "record")]
+    recorder: Option<crate::record::Recorder>,
+    /// Whether IMEs may be used to type, e.g. while a text field is focused
+    ime_allowed: bool,
+    /// Whether the next frame drawn is saved as a screenshot
+    screenshot_requested: bool,
+    /// Whether the cursor is hidden and locked (or confined) to the window for mouse look
+    cursor_grabbed: bool,
+    /// `None` if gamepads couldn't be initialized on this platform
+    #[cfg(feature = "gamepad")]
+    gilrs: Option<gilrs::Gilrs>,
 
     performance_stats: PerformanceStats,
+
+    /// Exits when pressed, if set
+    quit_key: Option<winit::keyboard::KeyCode>,
+    on_close_requested: Option<CloseHandler>,
+    render_callback: Option<RenderCallback>,
+    update_callback: Option<UpdateCallback>,
+    /// Set by the update callback; acted on once the frame's updates are done
+    exit_requested: bool,
+
+    #[cfg(feature = "egui")]
+    ui: Option<Ui>,
+    /// Builds the immediate-mode UI each frame
+    #[cfg(feature = "egui")]
+    on_gui: Option<GuiCallback>,
+    /// Whether the built-in settings window is shown; toggled with F1
+    #[cfg(feature = "egui")]
+    settings_panel: bool,
 }
 
 impl<'a> Engine<'a> {
     // CONFIGURATION
 
     pub fn new(window_attributes: winit::window::WindowAttributes) -> Self {
-        async {
-            Self {
-                window_attributes,
-                window: None,
-                has_focus: false,
-                graphics_context: None,
-                graphics_settings: Default::default(),
-                next_frame_time: Instant::now(),
-                performance_stats: Default::default(),
-            }
+        Self {
+            window_attributes,
+            window_settings: Default::default(),
+            window: None,
+            has_focus: false,
+            deferred_show: false,
+            occluded: false,
+            windowed_size: None,
+            scale_factor: 1.0,
+            size: Default::default(),
+            surface_size: Default::default(),
+            pending_resize: None,
+            windows: HashMap::new(),
+            pending_windows: vec![],
+            graphics_context: None,
+            graphics_settings: Default::default(),
+            #[cfg(target_arch = "wasm32")]
+            pending_graphics_context: Default::default(),
+            next_frame_time: Instant::now(),
+            last_update: Instant::now(),
+            delta_time: Duration::ZERO,
+            paused: false,
+            paused_by_focus: false,
+            update_accumulator: Duration::ZERO,
+            interpolation_alpha: 0.0,
+            last_timing_log: Instant::now(),
+            last_budget_warning: None,
+            camera: Default::default(),
+            camera_2d: Default::default(),
+            orbit_camera: Default::default(),
+            active_camera: Default::default(),
+            input: Default::default(),
+            console: Default::default(),
+            asset_events: std::sync::mpsc::channel(),
+            #[cfg(feature = "record")]
+            recorder: None,
+            ime_allowed: false,
+            screenshot_requested: false,
+            cursor_grabbed: false,
+            #[cfg(feature = "gamepad")]
+            gilrs: gilrs::Gilrs::new()
+                .inspect_err(|error| warn!("Gamepads are unavailable: {error}"))
+                .ok(),
+            performance_stats: Default::default(),
+            quit_key: Some(winit::keyboard::KeyCode::Escape),
+            on_close_requested: None,
+            render_callback: None,
+            update_callback: None,
+            exit_requested: false,
+            #[cfg(feature = "egui")]
+            ui: None,
+            #[cfg(feature = "egui")]
+            on_gui: None,
+            #[cfg(feature = "egui")]
+            settings_panel: false,
         }
-        .block_on()
     }
 
     pub fn with_graphics_settings(mut self, graphics_settings: GraphicsSettings) -> Self {
+        self.performance_stats = PerformanceStats::new(graphics_settings.performance_stats);
         self.graphics_settings = graphics_settings;
         self
     }
 
+    pub fn with_window_settings(mut self, window_settings: WindowSettings) -> Self {
+        self.window_settings = window_settings;
+        self
+    }
+
+    /// Initializes logging with `level` unless `RUST_LOG` is set, which takes precedence, and
+    /// mirrors it to the console. Does nothing if a logger was already set up.
+    pub fn with_log_level(self, level: log::LevelFilter) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let mut builder = pretty_env_logger::formatted_timed_builder();
+            match std::env::var("RUST_LOG") {
+                Ok(filters) => builder.parse_filters(&filters),
+                Err(_) => builder.filter_level(level),
+            };
+            let logger = builder.build();
+            let max_level = logger.filter();
+            if log::set_boxed_logger(Box::new(console::ConsoleLogger::new(logger))).is_ok() {
+                log::set_max_level(max_level);
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        log::set_max_level(level);
+        self
+    }
+
+    /// Sets the initial inner size of the window; an empty size is ignored with a warning.
+    pub fn with_window_size(mut self, size: impl Into<winit::dpi::Size>) -> Self {
+        if let Some(size) = Self::non_zero_size(size.into()) {
+            self.window_attributes.inner_size = Some(size);
+        }
+        self
+    }
+
+    /// Sets the smallest inner size the window can be resized to; an empty size is ignored with
+    /// a warning.
+    pub fn with_min_size(mut self, size: impl Into<winit::dpi::Size>) -> Self {
+        if let Some(size) = Self::non_zero_size(size.into()) {
+            self.window_attributes.min_inner_size = Some(size);
+        }
+        self
+    }
+
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.window_attributes.resizable = resizable;
+        self
+    }
+
+    /// Whether the window has a title bar and borders
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.window_attributes.decorations = decorations;
+        self
+    }
+
+    pub fn with_maximized(mut self, maximized: bool) -> Self {
+        self.window_attributes.maximized = maximized;
+        self
+    }
+
+    /// Sets the window's icon from an image file, e.g. a PNG. If it can't be loaded, the
+    /// error is logged and the window keeps the default icon. Unsupported on the web and macOS.
+    pub fn with_icon(mut self, path: impl AsRef<std::path::Path>) -> Self {
+        let path = path.as_ref();
+        let image = match image::open(path) {
+            Ok(image) => image.to_rgba8(),
+            Err(error) => {
+                warn!("Failed to load window icon '{}': {error}", path.display());
+                return self;
+            }
+        };
+
+        let (width, height) = image.dimensions();
+        match winit::window::Icon::from_rgba(image.into_raw(), width, height) {
+            Ok(icon) => self.window_attributes.window_icon = Some(icon),
+            Err(error) => warn!("Invalid window icon '{}': {error}", path.display()),
+        }
+        self
+    }
+
+    /// Keeps the window hidden until a first frame has been drawn into it, so it never shows a
+    /// blank or half-initialized surface on startup.
+    pub fn with_deferred_show(mut self, deferred_show: bool) -> Self {
+        self.deferred_show = deferred_show;
+        self
+    }
+
+    fn non_zero_size(size: winit::dpi::Size) -> Option<winit::dpi::Size> {
+        let physical = size.to_physical::<u32>(1.0);
+        if physical.width == 0 || physical.height == 0 {
+            warn!("Ignoring empty window size {size:?}.");
+            return None;
+        }
+        Some(size)
+    }
+
+    /// Sets the key that exits, Escape by default, or `None` for no quit key.
+    pub fn with_quit_key(mut self, quit_key: Option<winit::keyboard::KeyCode>) -> Self {
+        self.quit_key = quit_key;
+        self
+    }
+
+    /// Sets a callback that can veto exiting, e.g. to ask about unsaved changes first.
+    pub fn with_close_handler(
+        mut self,
+        on_close_requested: impl FnMut() -> bool + 'static,
+    ) -> Self {
+        self.on_close_requested = Some(Callback::new(Box::new(on_close_requested)));
+        self
+    }
+
+    /// Sets a callback running application logic every fixed update step, with the step's
+    /// duration (see [`GraphicsSettings::update_rate`]).
+    ///
+    /// Each frame, input events have already been received and gamepads polled, then the
+    /// hotkeys and camera controls run, then every update step due calls this callback after
+    /// the built-in camera movement, and finally the frame is drawn. No steps run while paused.
+    pub fn with_update(
+        mut self,
+        update: impl FnMut(&mut UpdateContext, Duration) + 'static,
+    ) -> Self {
+        self.update_callback = Some(Callback::new(Box::new(update)));
+        self
+    }
+
+    /// Sets a callback invoked every frame inside the main render pass, after the built-in
+    /// objects are drawn, to set pipelines and bind groups and issue draws of its own.
+    pub fn with_render(mut self, render: impl FnMut(&mut DrawContext) + 'static) -> Self {
+        self.render_callback = Some(Callback::new(Box::new(render)));
+        self
+    }
+
+    /// Sets a callback invoked every frame to build an immediate-mode UI drawn over the scene.
+    /// Input the UI consumes (e.g. clicks on a window) isn't passed on to the engine.
+    #[cfg(feature = "egui")]
+    pub fn with_gui(mut self, on_gui: impl FnMut(&egui::Context) + 'static) -> Self {
+        self.on_gui = Some(Callback::new(Box::new(on_gui)));
+        self
+    }
+
+    /// Shows a built-in window for changing e.g. the clear color and framerate cap at runtime.
+    #[cfg(feature = "egui")]
+    pub fn with_settings_panel(mut self, settings_panel: bool) -> Self {
+        self.settings_panel = settings_panel;
+        self
+    }
+
+    /// Caps the framerate to `fps` with vsync off, or uncaps it with vsync on if `None`. A
+    /// framerate that isn't positive is ignored with a warning.
+    pub fn set_framerate(&mut self, fps: Option<f64>) {
+        let frametime = match fps {
+            Some(fps) => match crate::settings::frametime_at(fps) {
+                Some(frametime) => Some(frametime),
+                None => {
+                    warn!("Ignoring invalid framerate {fps}.");
+                    return;
+                }
+            },
+            None => None,
+        };
+        self.graphics_settings.refresh_rate_pacing = false;
+        self.graphics_settings.frametime_or_vsync = frametime;
+        self.next_frame_time = Instant::now();
+
+        if let Some(gc) = &mut self.graphics_context {
+            gc.set_vsync(fps.is_none());
+        }
+    }
+
+    /// With refresh rate pacing, matches the frametime to the window's current monitor.
+    fn update_refresh_rate_pacing(&mut self) {
+        if !self.graphics_settings.refresh_rate_pacing {
+            return;
+        }
+        let Some(window) = &self.window else {
+            return;
+        };
+
+        let refresh_rate = window
+            .current_monitor()
+            .and_then(|monitor| monitor.refresh_rate_millihertz())
+            .filter(|&millihertz| millihertz > 0)
+            .map(|millihertz| millihertz as f64 / 1000.0);
+        let frametime = crate::settings::frametime_at(
+            refresh_rate.unwrap_or(crate::settings::FALLBACK_REFRESH_RATE),
+        )
+        .expect("Refresh rates are positive.");
+        if self.graphics_settings.frametime_or_vsync == Some(frametime) {
+            return;
+        }
+
+        match refresh_rate {
+            Some(refresh_rate) => info!("Pacing frames to the {refresh_rate} Hz refresh rate."),
+            None => warn!(
+                "Refresh rate unavailable, pacing frames to {} fps.",
+                crate::settings::FALLBACK_REFRESH_RATE
+            ),
+        }
+        self.graphics_settings.frametime_or_vsync = Some(frametime);
+        self.next_frame_time = Instant::now();
+    }
+
+    /// Applies changes made directly to the graphics settings (by the update callback or
+    /// settings panel) since they had the given values.
+    fn apply_settings_changes(
+        &mut self,
+        clear_color: wgpu::Color,
+        frametime_or_vsync: Option<Duration>,
+    ) {
+        if self.graphics_settings.clear_color != clear_color {
+            if let Some(gc) = &mut self.graphics_context {
+                gc.set_clear_color(self.graphics_settings.clear_color);
+            }
+        }
+        if self.graphics_settings.frametime_or_vsync != frametime_or_vsync {
+            let framerate = self
+                .graphics_settings
+                .frametime_or_vsync
+                .map(|frametime| 1.0 / frametime.as_secs_f64());
+            self.set_framerate(framerate);
+        }
+    }
+
+    /// Switches to the framerate cap after the current one in [`FRAMERATE_CAPS`].
+    fn cycle_framerate(&mut self) {
+        let current = self
+            .graphics_settings
+            .frametime_or_vsync
+            .map(|frametime| (1.0 / frametime.as_secs_f64()).round());
+        let next = FRAMERATE_CAPS
+            .iter()
+            .position(|&cap| cap == current)
+            .map_or(0, |i| (i + 1) % FRAMERATE_CAPS.len());
+
+        self.set_framerate(FRAMERATE_CAPS[next]);
+        match FRAMERATE_CAPS[next] {
+            Some(fps) => info!("Framerate capped to {fps} fps."),
+            None => info!("Framerate uncapped (vsync)."),
+        }
+    }
+
+    // CAMERA
+
+    pub fn with_active_camera(mut self, active_camera: ActiveCamera) -> Self {
+        self.active_camera = active_camera;
+        self
+    }
+
+    pub fn active_camera(&self) -> ActiveCamera {
+        self.active_camera
+    }
+
+    pub fn set_active_camera(&mut self, active_camera: ActiveCamera) {
+        self.active_camera = active_camera;
+    }
+
+    /// The 3D camera, flown with WASD and turned by dragging with the right mouse button
+    pub fn camera(&self) -> &Camera3D {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera3D {
+        &mut self.camera
+    }
+
+    pub fn camera_2d(&self) -> &Camera2D {
+        &self.camera_2d
+    }
+
+    /// Pan and zoom from game code; used while it is the [`ActiveCamera`].
+    pub fn camera_2d_mut(&mut self) -> &mut Camera2D {
+        &mut self.camera_2d
+    }
+
+    /// The orbit camera, rotated by dragging with the left mouse button and zoomed by scrolling
+    pub fn orbit_camera(&self) -> &OrbitCamera {
+        &self.orbit_camera
+    }
+
+    pub fn orbit_camera_mut(&mut self) -> &mut OrbitCamera {
+        &mut self.orbit_camera
+    }
+
+    // INPUT
+
+    /// Lets the user compose text with an IME, e.g. while a text field is focused. Off by
+    /// default so IME popups don't get in the way of game controls.
+    pub fn set_ime_allowed(&mut self, allowed: bool) {
+        self.ime_allowed = allowed;
+        if let Some(window) = &self.window {
+            window.set_ime_allowed(allowed);
+        }
+    }
+
+    /// Hides the cursor and locks it in place for mouse look, or releases it. Where locking
+    /// isn't supported the cursor is confined to the window instead; either way the camera
+    /// turns by raw mouse motion while grabbed, without holding the right mouse button. Released
+    /// automatically when the window loses focus.
+    pub fn set_cursor_grab(&mut self, grab: bool) {
+        use winit::window::CursorGrabMode;
+
+        let Some(window) = &self.window else {
+            return;
+        };
+        let result = if grab {
+            window
+                .set_cursor_grab(CursorGrabMode::Locked)
+                .or_else(|_| window.set_cursor_grab(CursorGrabMode::Confined))
+        } else {
+            window.set_cursor_grab(CursorGrabMode::None)
+        };
+        if let Err(error) = result {
+            warn!("Failed to change the cursor grab: {error}");
+            return;
+        }
+
+        window.set_cursor_visible(!grab);
+        self.cursor_grabbed = grab;
+    }
+
+    /// Whether the cursor is grabbed, in which case mouse movement should be read from
+    /// [`InputState::mouse_motion`]
+    pub fn cursor_grabbed(&self) -> bool {
+        self.cursor_grabbed
+    }
+
+    pub fn input(&self) -> &InputState {
+        &self.input
+    }
+
+    /// The state of a connected gamepad, `None` once it disconnects
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad(&self, id: gilrs::GamepadId) -> Option<&input::GamepadState> {
+        self.input.gamepad(id)
+    }
+
+    /// Sets the fraction of each gamepad axis' range around the center that reads as zero,
+    /// [`input::DEFAULT_DEADZONE`] by default
+    #[cfg(feature = "gamepad")]
+    pub fn with_gamepad_deadzone(mut self, deadzone: f32) -> Self {
+        self.input.set_gamepad_deadzone(deadzone);
+        self
+    }
+
+    /// Folds gamepad events since the last frame into the input state.
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepads(&mut self) {
+        let Some(gilrs) = &mut self.gilrs else {
+            return;
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::Connected => {
+                    info!("Gamepad connected: {}", gilrs.gamepad(event.id).name())
+                }
+                gilrs::EventType::Disconnected => info!("Gamepad {} disconnected.", event.id),
+                _ => {}
+            }
+            self.input.on_gamepad_event(&event);
+        }
+    }
+
+    /// Handles the built-in hotkeys, each firing once per press rather than on key repeat.
+    fn handle_hotkeys(&mut self) {
+        use winit::keyboard::KeyCode;
+
+        if self.input.just_pressed(KeyCode::Backquote) {
+            self.console.toggle();
+        }
+        let quit = self
+            .quit_key
+            .is_some_and(|key| self.input.just_pressed(key));
+        // Keys type into the console while it's open, except the quit key, which closes it.
+        if self.console.is_open() {
+            if quit {
+                self.console.close();
+            } else if let Some(line) = self.console.on_text_events(self.input.text_events()) {
+                self.run_command(&line);
+            }
+            return;
+        }
+
+        if quit || (self.input.ctrl() && self.input.just_pressed(KeyCode::KeyQ)) {
+            // Acted on once this frame's updates are done.
+            self.exit_requested = true;
+        }
+        if self.input.alt() && self.input.just_pressed(KeyCode::Enter) {
+            self.toggle_fullscreen();
+        }
+        if self.input.just_pressed(KeyCode::KeyP) {
+            if let Some(gc) = &mut self.graphics_context {
+                match gc.cycle_solo_shader() {
+                    Some(shader) => info!("Soloing shader '{}'.", shader.name),
+                    None => info!("Drawing all shaders."),
+                }
+            }
+        }
+        if self.input.just_pressed(KeyCode::KeyV) {
+            self.cycle_framerate();
+        }
+        if self.input.just_pressed(KeyCode::Pause) {
+            if self.paused {
+                self.resume();
+            } else {
+                self.pause();
+            }
+        }
+        if self.input.just_pressed(KeyCode::F5) {
+            if let Some(gc) = &mut self.graphics_context {
+                // Failures are logged along the way.
+                let _ = gc.reload_all_shaders();
+            }
+        }
+        if self.input.just_pressed(KeyCode::KeyL) {
+            if let Some(gc) = &mut self.graphics_context {
+                if self.input.shift() {
+                    gc.set_wireframe(!gc.wireframe());
+                    info!("Wireframe mode: {}", gc.wireframe());
+                } else {
+                    let enabled = gc.toggle_wireframe_overlay();
+                    info!("Wireframe overlay: {enabled}");
+                }
+            }
+        }
+        if self.input.just_pressed(KeyCode::Equal) {
+            self.camera
+                .set_target_fov(self.camera.target_fov() - FOV_STEP);
+        }
+        if self.input.just_pressed(KeyCode::Minus) {
+            self.camera
+                .set_target_fov(self.camera.target_fov() + FOV_STEP);
+        }
+
+        if self.input.just_pressed(KeyCode::KeyF) {
+            match self.performance_stats.get_frame_time() {
+                Some(frame_time) => {
+                    debug!("Framerate: {:.3} fps", 1.0 / frame_time.as_secs_f64());
+                    if let (Some(p95), Some(p99)) = (
+                        self.performance_stats.percentile(95.0),
+                        self.performance_stats.percentile(99.0),
+                    ) {
+                        debug!("Frame time p95: {p95:.2?}, p99: {p99:.2?}");
+                    }
+                    if let Some(ratio) = self.performance_stats.missed_frame_ratio() {
+                        debug!(
+                            "{:.1}% missed frames over last {}.",
+                            ratio * 100.0,
+                            self.performance_stats.recorded_frame_count()
+                        );
+                    }
+                    self.log_frame_timings();
+                    if let Some(gpu) = self
+                        .graphics_context
+                        .as_ref()
+                        .and_then(GraphicsContext::gpu_pass_time)
+                    {
+                        debug!("GPU pass: {gpu:.2?}");
+                    }
+                }
+                None => debug!("Performance stats are disabled."),
+            }
+        }
+
+        if self.input.just_pressed(KeyCode::F2) {
+            // Taken once the frame is drawn.
+            self.screenshot_requested = true;
+        }
+        if self.input.just_pressed(KeyCode::F3) {
+            self.toggle_overlay();
+        }
+        #[cfg(feature = "egui")]
+        if self.input.just_pressed(KeyCode::F1) {
+            self.settings_panel = !self.settings_panel;
+        }
+    }
+
+    // CONSOLE
+
+    /// Adds a console command, run by typing `name` followed by its whitespace-separated
+    /// arguments. Replaces built-in commands and earlier ones of the same name.
+    pub fn register_command(&mut self, name: impl Into<String>, command: Box<console::CommandFn>) {
+        self.console.register(name, console::Command::new(command));
+    }
+
+    /// Runs a line typed into the console, falling back to the built-in commands.
+    fn run_command(&mut self, line: &str) {
+        console::print(format!("> {line}"));
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return;
+        };
+        let args: Vec<_> = words.collect();
+        if self.console.run(name, &args) {
+            return;
+        }
+
+        match (name, args.as_slice()) {
+            ("help", _) => {
+                let mut commands = vec!["clear", "clear_color", "help", "set_framerate"];
+                commands.extend(self.console.commands());
+                console::print(format!("Commands: {}", commands.join(", ")));
+            }
+            ("clear", _) => console::clear(),
+            ("set_framerate", ["vsync"]) => self.set_framerate(None),
+            ("set_framerate", [fps]) => match fps.parse::<f64>() {
+                Ok(fps) if fps > 0.0 => self.set_framerate(Some(fps)),
+                _ => console::print("Usage: set_framerate <fps> | vsync"),
+            },
+            ("clear_color", [r, g, b] | [r, g, b, _]) => {
+                let a = args.get(3).copied().unwrap_or("1");
+                match [r, g, b, &a].map(|c| c.parse::<f64>()) {
+                    [Ok(r), Ok(g), Ok(b), Ok(a)] => {
+                        self.graphics_settings.clear_color = wgpu::Color { r, g, b, a };
+                        if let Some(gc) = &mut self.graphics_context {
+                            gc.set_clear_color(self.graphics_settings.clear_color);
+                        }
+                    }
+                    _ => console::print("Usage: clear_color <r> <g> <b> [a]"),
+                }
+            }
+            ("set_framerate", _) => console::print("Usage: set_framerate <fps> | vsync"),
+            ("clear_color", _) => console::print("Usage: clear_color <r> <g> <b> [a]"),
+            _ => console::print(format!("Unknown command '{name}', try 'help'.")),
+        }
+    }
+
+    /// Hands the frame just drawn to the screenshot and recording waiting on it, if any.
+    fn handle_capture(&mut self) {
+        let Some(capture) = self
+            .graphics_context
+            .as_mut()
+            .and_then(GraphicsContext::take_capture)
+        else {
+            return;
+        };
+
+        let image = match capture {
+            Ok(image) => image,
+            Err(error) => {
+                error!("Failed to capture frame: {error:?}");
+                self.screenshot_requested = false;
+                #[cfg(feature = "record")]
+                self.stop_recording();
+                return;
+            }
+        };
+        if std::mem::take(&mut self.screenshot_requested) {
+            Self::save_screenshot(&image);
+        }
+        #[cfg(feature = "record")]
+        self.record_frame(&image);
+    }
+
+    /// Saves `image` to `screenshot-<unix millis>.png` in the working directory.
+    fn save_screenshot(image: &image::RgbaImage) {
+        let timestamp = web_time::SystemTime::now()
+            .duration_since(web_time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let path = format!("screenshot-{timestamp}.png");
+
+        match image.save(&path) {
+            Ok(()) => info!("Saved screenshot to '{path}'."),
+            Err(error) => error!("Failed to save screenshot: {error:?}"),
+        }
+    }
+
+    // RECORDING
+
+    /// Records the next `frames` frames to a video at `path` through `ffmpeg`, e.g. `demo.mp4`.
+    ///
+    /// Frames are the scene without overlays at [`RECORD_FRAMERATE`], and the simulation advances
+    /// by exactly one video frame per drawn frame, so the video is smooth however fast the display
+    /// is. Any recording in progress is finished first.
+    #[cfg(feature = "record")]
+    pub fn record(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+        frames: u32,
+    ) -> Result<(), crate::util::error::RecordError> {
+        use crate::util::error::RecordError;
+
+        self.stop_recording();
+        if self.graphics_context.is_none() {
+            return Err(RecordError::NoGraphicsContext);
+        }
+
+        let path = path.as_ref();
+        self.recorder = Some(crate::record::Recorder::start(
+            path,
+            self.surface_size.width,
+            self.surface_size.height,
+            RECORD_FRAMERATE,
+            frames,
+        )?);
+        info!("Recording {frames} frames to '{}'.", path.display());
+        Ok(())
+    }
+
+    #[cfg(feature = "record")]
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    /// Finishes the recording in progress, if any, waiting for the video to be written.
+    #[cfg(feature = "record")]
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            match recorder.finish() {
+                Ok(()) => info!("Finished recording."),
+                Err(error) => error!("Failed to finish recording: {error:?}"),
+            }
+        }
+    }
+
+    /// Writes the frame just drawn into the recording, finishing it after the last frame.
+    #[cfg(feature = "record")]
+    fn record_frame(&mut self, frame: &image::RgbaImage) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+
+        match recorder.write_frame(frame) {
+            Ok(false) => {}
+            Ok(true) => self.stop_recording(),
+            Err(error) => {
+                error!("Recording failed: {error:?}");
+                self.stop_recording();
+            }
+        }
+    }
+
+    // UPDATING
+
+    /// Stops updating the simulation; frames keep being drawn showing its last state.
+    pub fn pause(&mut self) {
+        if !self.paused {
+            info!("Paused.");
+        }
+        self.paused = true;
+        self.paused_by_focus = false;
+    }
+
+    pub fn resume(&mut self) {
+        if self.paused {
+            info!("Resumed.");
+        }
+        self.paused = false;
+        self.paused_by_focus = false;
+        // Don't catch up on the time spent paused.
+        self.update_accumulator = Duration::ZERO;
+        self.last_update = Instant::now();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Runs as many fixed timestep updates as the time since the last frame calls for.
+    fn advance(&mut self) {
+        let now = Instant::now();
+        if self.paused {
+            self.delta_time = Duration::ZERO;
+            self.last_update = now;
+            return;
+        }
+
+        self.delta_time = (now - self.last_update).min(MAX_DELTA_TIME);
+        #[cfg(feature = "record")]
+        if let Some(recorder) = &self.recorder {
+            self.delta_time = recorder.frametime();
+        }
+        self.update_accumulator += self.delta_time;
+        self.last_update = now;
+
+        // Mouse movement is already a total for the frame, so it's applied once rather than
+        // per step.
+        // Raw motion rather than cursor movement, so looking isn't accelerated or stopped by the
+        // screen edge.
+        let look = (self.cursor_grabbed
+            || self.input.is_button_held(winit::event::MouseButton::Right))
+        .then(|| self.input.mouse_motion());
+        if let (ActiveCamera::Camera3D, Some((dx, dy))) = (self.active_camera, look) {
+            self.camera.rotate(
+                dx as f32 * MOUSE_SENSITIVITY,
+                -dy as f32 * MOUSE_SENSITIVITY,
+            );
+        }
+        if self.active_camera == ActiveCamera::Orbit {
+            self.orbit_camera.handle_input(&self.input);
+        }
+        #[cfg(feature = "gamepad")]
+        if self.active_camera == ActiveCamera::Camera3D {
+            if let Some((_, gamepad)) = self.input.gamepads().next() {
+                let (x, y) = gamepad.right_stick();
+                let turn = STICK_TURN_SPEED * self.delta_time.as_secs_f32();
+                self.camera.rotate(x * turn, y * turn);
+            }
+        }
+
+        // The field is public, so it may not have been validated.
+        let step = crate::settings::frametime_at(self.graphics_settings.update_rate)
+            .filter(|step| !step.is_zero())
+            .unwrap_or_else(|| Duration::from_secs_f64(1.0 / crate::settings::DEFAULT_UPDATE_RATE));
+        let (updates, skipped, remainder) = fixed_steps(
+            self.update_accumulator,
+            step,
+            self.graphics_settings.max_updates_per_frame,
+        );
+        if !skipped.is_zero() {
+            // Catching up would only make this frame slower and fall further behind.
+            warn!("Fell {skipped:.2?} behind, skipping those updates.");
+        }
+        for _ in 0..updates {
+            self.update(step);
+        }
+        self.update_accumulator = remainder;
+        self.interpolation_alpha = self.update_accumulator.as_secs_f64() / step.as_secs_f64();
+    }
+
+    /// Advances the simulation by exactly `dt`, one update step.
+    fn update(&mut self, dt: Duration) {
+        self.camera.update(dt);
+
+        if self.active_camera == ActiveCamera::Camera3D && !self.console.is_open() {
+            use winit::keyboard::KeyCode;
+
+            let axis = |positive, negative| {
+                self.input.is_held(positive) as i32 as f32
+                    - self.input.is_held(negative) as i32 as f32
+            };
+            let direction = self.camera.forward() * axis(KeyCode::KeyW, KeyCode::KeyS)
+                + self.camera.right() * axis(KeyCode::KeyD, KeyCode::KeyA);
+            #[allow(unused_mut)]
+            let mut velocity = direction.normalize_or_zero();
+            // The left stick moves at up to full speed depending on how far it's pushed.
+            #[cfg(feature = "gamepad")]
+            if let Some((_, gamepad)) = self.input.gamepads().next() {
+                let (x, y) = gamepad.left_stick();
+                velocity = (velocity + self.camera.forward() * y + self.camera.right() * x)
+                    .clamp_length_max(1.0);
+            }
+            self.camera.position += velocity * CAMERA_SPEED * dt.as_secs_f32();
+        }
+
+        if let Some(update) = &mut self.update_callback {
+            update(
+                &mut UpdateContext {
+                    input: &self.input,
+                    camera: &mut self.camera,
+                    camera_2d: &mut self.camera_2d,
+                    orbit_camera: &mut self.orbit_camera,
+                    active_camera: &mut self.active_camera,
+                    settings: &mut self.graphics_settings,
+                    exit_requested: &mut self.exit_requested,
+                },
+                dt,
+            );
+        }
+    }
+
+    /// Time since the previous frame, capped at 250ms
+    pub fn delta_time(&self) -> Duration {
+        self.delta_time
+    }
+
+    /// How far the current frame is between the last and next update, in `[0, 1)`, for
+    /// interpolating state when rendering
+    pub fn interpolation_alpha(&self) -> f64 {
+        self.interpolation_alpha
+    }
+
     // DRAWING
 
     fn can_draw(&self) -> bool {
         Instant::now() >= self.next_frame_time
     }
 
+    pub fn performance_stats(&self) -> &PerformanceStats {
+        &self.performance_stats
+    }
+
+    /// Logs the average swapchain acquire and CPU render times.
+    fn log_frame_timings(&self) {
+        if let (Some(acquire), Some(cpu)) = (
+            self.performance_stats.get_acquire_time(),
+            self.performance_stats.get_cpu_time(),
+        ) {
+            debug!("Acquire: {acquire:.2?}, CPU: {cpu:.2?}");
+        }
+    }
+
+    /// Warns, at most once a second, if a frame's work took longer than the framerate cap allows.
+    fn check_frame_budget(&mut self, work: Duration) {
+        let Some(budget) = self.graphics_settings.frametime_or_vsync else {
+            return;
+        };
+        if work <= budget
+            || self
+                .last_budget_warning
+                .is_some_and(|last| last.elapsed() < BUDGET_WARNING_INTERVAL)
+        {
+            return;
+        }
+
+        self.last_budget_warning = Some(Instant::now());
+        warn!(
+            "Frame took {work:.2?}, {:.2?} over the {budget:.2?} budget.",
+            work - budget
+        );
+    }
+
+    /// Sprites drawn and cleared by the next [`Engine::draw`], once the graphics context exists
+    pub fn sprites_mut(&mut self) -> Option<&mut SpriteBatch> {
+        self.graphics_context
+            .as_mut()
+            .map(GraphicsContext::sprites_mut)
+    }
+
+    /// Shows or hides the frame stats overlay.
+    pub fn toggle_overlay(&mut self) {
+        self.graphics_settings.overlay = !self.graphics_settings.overlay;
+    }
+
+    /// Text of the frame stats overlay
+    fn overlay_text(stats: &PerformanceStats, gpu: &str) -> String {
+        let timings = match (
+            stats.get_frame_time(),
+            stats.percentile(99.0),
+            stats.get_cpu_time(),
+            stats.get_acquire_time(),
+        ) {
+            (Some(frame_time), Some(p99), Some(cpu), Some(acquire)) => format!(
+                "{:.0} fps, p99 {p99:.2?}\nCPU {cpu:.2?}, acquire {acquire:.2?}",
+                1.0 / frame_time.as_secs_f64()
+            ),
+            _ => "Performance stats are disabled.".to_owned(),
+        };
+
+        format!("{timings}\n{gpu}")
+    }
+
     pub fn draw(&mut self) -> Result<(), DrawError> {
-        self.graphics_context.as_mut().unwrap().draw();
+        #[cfg(feature = "egui")]
+        if let Some(ui) = &mut self.ui {
+            let (clear_color, frametime) = (
+                self.graphics_settings.clear_color,
+                self.graphics_settings.frametime_or_vsync,
+            );
+
+            ui.run(self.window.as_ref().unwrap(), |ctx| {
+                if let Some(on_gui) = &mut self.on_gui {
+                    on_gui(ctx);
+                }
+                if self.settings_panel {
+                    crate::ui::settings_panel(ctx, &mut self.graphics_settings);
+                }
+            });
+
+            // Apply whatever the settings panel changed.
+            self.apply_settings_changes(clear_color, frametime);
+        }
+
+        let Some(gc) = self.graphics_context.as_mut() else {
+            return Ok(());
+        };
+        #[cfg(feature = "record")]
+        let recording = self.recorder.is_some();
+        #[cfg(not(feature = "record"))]
+        let recording = false;
+        if self.screenshot_requested || recording {
+            gc.request_capture();
+        }
+        // The console takes the overlay's place while open.
+        let overlay_text = if self.console.is_open() {
+            Some(self.console.text())
+        } else {
+            self.graphics_settings
+                .overlay
+                .then(|| Self::overlay_text(&self.performance_stats, &gc.adapter_info().name))
+        };
+        gc.set_overlay_text(overlay_text.as_deref());
+        let aspect = self.surface_size.width as f32 / self.surface_size.height.max(1) as f32;
+        gc.set_view_projection(match self.active_camera {
+            ActiveCamera::Camera2D => self.camera_2d.view_projection(aspect),
+            ActiveCamera::Camera3D => self.camera.view_projection(aspect),
+            ActiveCamera::Orbit => self.orbit_camera.view_projection(aspect),
+        });
+        let render_callback = &mut self.render_callback;
+        let result = gc.draw_with(
+            |draw| {
+                if let Some(render) = render_callback {
+                    render(draw);
+                }
+            },
+            |_device, _queue, _encoder, _view| {
+                #[cfg(feature = "egui")]
+                if let Some(ui) = &mut self.ui {
+                    ui.render(_device, _queue, _encoder, _view);
+                }
+            },
+        );
+
+        let timings = result?;
+        self.performance_stats
+            .set_target_frame_time(self.graphics_settings.frametime_or_vsync);
         self.performance_stats.add_frame(Instant::now());
+        self.performance_stats
+            .add_frame_timings(timings.acquire, timings.cpu);
+
+        if self.graphics_settings.present_timing_diagnostics
+            && self.last_timing_log.elapsed() >= TIMING_LOG_INTERVAL
+        {
+            self.last_timing_log = Instant::now();
+            self.log_frame_timings();
+        }
+
+        Ok(())
+    }
+
+    /// Recovers from `error` so drawing can resume next frame, or returns it if it's fatal.
+    fn recover_from(&mut self, error: DrawError) -> Result<(), DrawError> {
+        match error {
+            DrawError::SurfaceLost => {
+                // e.g. after a minimize or GPU reset.
+                if let Some(gc) = &mut self.graphics_context {
+                    gc.reconfigure_surface();
+                }
+                Ok(())
+            }
+            DrawError::Timeout => {
+                warn!("Timed out acquiring the next frame, skipping it.");
+                Ok(())
+            }
+            DrawError::OutOfMemory => Err(error),
+        }
+    }
+
+    // RESIZING
+
+    /// Reconfigures the surface for `size` and immediately presents a frame at it.
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
+        self.surface_size = size;
+
+        if let Some(gc) = &mut self.graphics_context {
+            gc.resize(size.width, size.height);
+
+            // Present a frame at the new size before returning, so the compositor never
+            // shows a stale or cleared one stretched to the wrong size while resizing.
+            if size.width > 0 && size.height > 0 {
+                if let Err(error) = self.draw().or_else(|error| self.recover_from(error)) {
+                    error!("Draw Error: {error:?}");
+                }
+            }
+        }
+    }
+
+    // WINDOW
+
+    /// Switches between borderless fullscreen on the current monitor and windowed mode.
+    pub fn toggle_fullscreen(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+
+        // Either way the surface is reconfigured for the new size by the `Resized` that follows.
+        match self.windowed_size.take() {
+            Some(windowed_size) => {
+                window.set_fullscreen(None);
+                let _ = window.request_inner_size(windowed_size);
+            }
+            None => {
+                self.windowed_size = Some(window.inner_size());
+                window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+            }
+        }
+    }
+
+    /// Physical pixels per logical pixel, e.g. `2.0` on HiDPI displays, for laying out UI
+    pub fn scale_factor(&self) -> f64 {
+        self.scale_factor
+    }
+
+    /// Inner size of the window in physical pixels, e.g. for layout and picking; zero until the
+    /// window exists and while minimized on some platforms
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.size
+    }
+
+    /// Width / height of the window, `1.0` while it has no area
+    pub fn aspect_ratio(&self) -> f32 {
+        if self.size.width == 0 || self.size.height == 0 {
+            return 1.0;
+        }
+        self.size.width as f32 / self.size.height as f32
+    }
+
+    // ASSETS
+
+    /// Every asset reloaded from disk since the last call, oldest first
+    pub fn drain_asset_events(&self) -> impl Iterator<Item = AssetReloaded> + '_ {
+        self.asset_events.1.try_iter()
+    }
+
+    /// For reporting reloads of assets the engine doesn't manage itself to
+    /// [`Self::drain_asset_events`]
+    pub fn asset_event_sender(&self) -> AssetEventSender {
+        self.asset_events.0.clone()
+    }
+
+    // WINDOWS
+
+    /// Opens another window with its own graphics context once the event loop is running.
+    /// Closing it leaves the rest of the engine running.
+    /// Unsupported on the web.
+    pub fn open_window(&mut self, attributes: winit::window::WindowAttributes) {
+        self.pending_windows.push(attributes);
+    }
+
+    /// Ids of the open windows besides the main one
+    pub fn window_ids(&self) -> impl Iterator<Item = winit::window::WindowId> + '_ {
+        self.windows.keys().copied()
+    }
+
+    /// The graphics context of an extra window, to fill its scene
+    pub fn window_graphics_context_mut(
+        &mut self,
+        id: winit::window::WindowId,
+    ) -> Option<&mut GraphicsContext<'a>> {
+        self.windows.get_mut(&id).map(|(_, gc)| gc)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn open_pending_windows(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop) {
+        if !self.pending_windows.is_empty() {
+            warn!("Extra windows aren't supported on the web.");
+            self.pending_windows.clear();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open_pending_windows(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        for attributes in std::mem::take(&mut self.pending_windows) {
+            let window = match event_loop.create_window(attributes) {
+                Ok(window) => Arc::new(window),
+                Err(error) => {
+                    error!("Failed to open window: {error}");
+                    continue;
+                }
+            };
+            match GraphicsContext::new(&self.graphics_settings, window.clone()).block_on() {
+                Ok(gc) => {
+                    self.windows.insert(window.id(), (window, gc));
+                }
+                Err(error) => error!("Failed to create graphics context: {error:?}"),
+            }
+        }
+    }
+
+    /// Handles an event for one of the extra windows, which only draw their scene.
+    fn extra_window_event(
+        &mut self,
+        id: winit::window::WindowId,
+        event: winit::event::WindowEvent,
+    ) {
+        use winit::event::WindowEvent;
+
+        let Some((window, gc)) = self.windows.get_mut(&id) else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => {
+                self.windows.remove(&id);
+            }
+            WindowEvent::Resized(size) => {
+                gc.resize(size.width, size.height);
+                window.request_redraw();
+            }
+            // Requested after each main window frame, so every window is paced by it.
+            WindowEvent::RedrawRequested => {
+                if let Err(error) = gc.draw() {
+                    match DrawError::from(error) {
+                        DrawError::SurfaceLost => gc.reconfigure_surface(),
+                        error => warn!("Failed to draw window {id:?}: {error:?}"),
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Logs what the engine ended up running on, for bug reports.
+    fn log_startup_info(gc: &GraphicsContext) {
+        let adapter = gc.adapter_info();
+        info!("YAGVE v{}", env!("CARGO_PKG_VERSION"));
+        info!("  Backend:        {:?}", adapter.backend);
+        info!(
+            "  Adapter:        {} ({:?})",
+            adapter.name, adapter.device_type
+        );
+        info!(
+            "  Driver:         {} {}",
+            adapter.driver, adapter.driver_info
+        );
+        info!("  Surface format: {:?}", gc.format());
+        info!("  Present mode:   {:?}", gc.present_mode());
+    }
+
+    /// Takes over the main window's newly created graphics context, exiting if it failed.
+    fn finish_graphics_init(
+        &mut self,
+        event_loop: &winit::event_loop::ActiveEventLoop,
+        result: Result<GraphicsContext<'a>, EngineError>,
+    ) {
+        match result {
+            Ok(gc) => {
+                self.install_graphics_context(gc);
+                if self.deferred_show {
+                    // Also primes the pipelines and swapchain, so the first visible frame is on
+                    // time.
+                    if let Err(error) = self.draw() {
+                        warn!("Failed to draw the first frame: {error:?}");
+                    }
+                    self.window
+                        .as_ref()
+                        .unwrap()
+                        .set_visible(self.window_attributes.visible);
+                }
+            }
+            Err(error) => {
+                error!("Failed to create graphics context: {error:?}");
+                self.exit(event_loop);
+            }
+        }
+    }
+
+    /// Creates the main window's graphics context in the background, as the browser's event loop
+    /// can't be blocked; it's picked up once ready in `about_to_wait`.
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_graphics_init(&mut self, window: Arc<winit::window::Window>) {
+        let (settings, pending) = (
+            self.graphics_settings.clone(),
+            self.pending_graphics_context.clone(),
+        );
+        wasm_bindgen_futures::spawn_local(async move {
+            let result = GraphicsContext::new(&settings, window).await;
+            *pending.borrow_mut() = Some(result);
+        });
+    }
 
+    fn install_graphics_context(&mut self, mut gc: GraphicsContext<'a>) {
+        Self::log_startup_info(&gc);
+        gc.set_asset_event_sender(self.asset_events.0.clone());
+        self.graphics_context = Some(gc);
+
+        #[cfg(feature = "egui")]
+        // Even without a GUI callback, so the settings panel can be toggled on.
+        if let (Some(gc), Some(window)) = (&self.graphics_context, &self.window) {
+            self.ui = Some(Ui::new(window, gc.device(), gc.format()));
+        }
+    }
+
+    /// Rebuilds the main window's graphics context from the stored settings, e.g. after the
+    /// device was lost. Anything created on the old device, like loaded shaders, meshes and scene
+    /// objects, is gone and has to be created again.
+    pub fn recreate_graphics_context(&mut self) -> Result<(), EngineError> {
+        let Some(window) = self.window.clone() else {
+            return Ok(());
+        };
+        info!("Recreating the graphics context.");
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.rebuild_graphics_context(|settings| {
+            GraphicsContext::new(settings, window).block_on()
+        })?;
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.release_graphics_context();
+            self.spawn_graphics_init(window);
+        }
         Ok(())
     }
 
+    /// Replaces the main graphics context with the one `build` creates from the stored settings.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rebuild_graphics_context(
+        &mut self,
+        build: impl FnOnce(&GraphicsSettings) -> Result<GraphicsContext<'a>, EngineError>,
+    ) -> Result<(), EngineError> {
+        self.release_graphics_context();
+        let gc = build(&self.graphics_settings)?;
+        self.install_graphics_context(gc);
+        Ok(())
+    }
+
+    /// Drops the main graphics context, e.g. to release its surface before creating a new one
+    /// for the same window.
+    fn release_graphics_context(&mut self) {
+        self.graphics_context = None;
+        #[cfg(feature = "egui")]
+        {
+            self.ui = None;
+        }
+    }
+
     // EXITING
 
     fn exit(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        // Otherwise the video is left unplayable.
+        #[cfg(feature = "record")]
+        self.stop_recording();
         event_loop.exit();
     }
+
+    /// Exits unless the close handler vetoes it.
+    fn request_exit(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        if self
+            .on_close_requested
+            .as_mut()
+            .is_none_or(|on_close_requested| on_close_requested())
+        {
+            self.exit(event_loop);
+        }
+    }
 }
 
 impl<'a> winit::application::ApplicationHandler for Engine<'a> {
     fn resumed(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
         // Create a new window if needed.
         if self.window.is_none() {
+            let mut attributes = self.window_settings.apply(self.window_attributes.clone());
+            if self.deferred_show {
+                attributes.visible = false;
+            }
+            // Add the canvas to the page; its size is then the window's inner size.
+            #[cfg(target_arch = "wasm32")]
+            let attributes = {
+                use winit::platform::web::WindowAttributesExtWebSys as _;
+                attributes.with_append(true)
+            };
             self.window = Some(Arc::new(
-                event_loop
-                    .create_window(self.window_attributes.clone())
-                    .unwrap(), // We have serious issues.
+                event_loop.create_window(attributes).unwrap(), // We have serious issues.
             ));
-            self.graphics_context = Some(
-                GraphicsContext::new(
-                    &self.graphics_settings,
-                    self.window.as_ref().unwrap().clone(),
-                )
-                .block_on(),
-            )
+            self.size = self.window.as_ref().unwrap().inner_size();
+            self.surface_size = self.size;
+            self.scale_factor = self.window.as_ref().unwrap().scale_factor();
+            self.window
+                .as_ref()
+                .unwrap()
+                .set_ime_allowed(self.ime_allowed);
+            self.update_refresh_rate_pacing();
+
+            let window = self.window.as_ref().unwrap().clone();
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                let result = GraphicsContext::new(&self.graphics_settings, window).block_on();
+                self.finish_graphics_init(event_loop, result);
+            }
+            #[cfg(target_arch = "wasm32")]
+            self.spawn_graphics_init(window);
+        }
+
+        self.open_pending_windows(event_loop);
+    }
+
+    fn about_to_wait(&mut self, event_loop: &winit::event_loop::ActiveEventLoop) {
+        #[cfg(target_arch = "wasm32")]
+        if let Some(result) = self.pending_graphics_context.take() {
+            self.finish_graphics_init(event_loop, result);
+        }
+
+        self.open_pending_windows(event_loop);
+
+        if let (ControlFlow::WaitUntil(next_frame_time), Some(window)) =
+            (event_loop.control_flow(), &self.window)
+        {
+            if Instant::now() >= next_frame_time {
+                window.request_redraw();
+            }
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &winit::event_loop::ActiveEventLoop,
+        _device_id: winit::event::DeviceId,
+        event: winit::event::DeviceEvent,
+    ) {
+        // Devices report motion regardless of which window, if any, is focused. `CursorMoved`
+        // still tracks the cursor's position for UI.
+        if let winit::event::DeviceEvent::MouseMotion { delta } = event {
+            if self.has_focus {
+                self.input.on_mouse_motion(delta);
+            }
         }
     }
 
     fn window_event(
         &mut self,
         event_loop: &winit::event_loop::ActiveEventLoop,
-        _window_id: winit::window::WindowId,
+        window_id: winit::window::WindowId,
         event: winit::event::WindowEvent,
     ) {
-        use winit::event::{KeyEvent, WindowEvent};
-        use winit::keyboard::{KeyCode, PhysicalKey};
+        use winit::event::WindowEvent;
+
+        if self.windows.contains_key(&window_id) {
+            self.extra_window_event(window_id, event);
+            return;
+        }
+
+        // Let the UI intercept input first so e.g. typing in a text field doesn't move the camera.
+        #[cfg(feature = "egui")]
+        if let (Some(ui), Some(window)) = (&mut self.ui, &self.window) {
+            if ui.on_window_event(window, &event) {
+                if input::is_release(&event) {
+                    self.input.on_window_event(&event);
+                }
+                return;
+            }
+        }
+
+        self.input.on_window_event(&event);
 
         match event {
             WindowEvent::Focused(is_focused) => {
                 self.has_focus = is_focused;
+                // Don't keep the cursor trapped while the user is elsewhere.
+                if !is_focused && self.cursor_grabbed {
+                    self.set_cursor_grab(false);
+                }
+                if !is_focused && !self.paused {
+                    self.pause();
+                    self.paused_by_focus = true;
+                } else if is_focused && self.paused_by_focus {
+                    self.resume();
+                }
                 self.window.as_ref().unwrap().request_redraw();
             }
-            WindowEvent::CloseRequested => self.exit(event_loop),
+            WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                // Keeping the suggested physical size, the `Resized` that follows reconfigures
+                // the surface for it.
+                info!("Scale factor changed to {scale_factor}.");
+                self.scale_factor = scale_factor;
+                self.size = self.window.as_ref().unwrap().inner_size();
+                self.update_refresh_rate_pacing();
+            }
+            // The window may have moved onto a monitor with a different refresh rate.
+            WindowEvent::Moved(_) => self.update_refresh_rate_pacing(),
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = occluded;
+                if !occluded {
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            WindowEvent::CloseRequested => self.request_exit(event_loop),
             WindowEvent::RedrawRequested => 'block: {
-                if !(self.has_focus || self.graphics_settings.render_without_focus) {
-                    break 'block;
+                // Apply at most one resize per frame, however many `Resized` events arrived.
+                if let Some((size, requested_at)) = self.pending_resize {
+                    if self
+                        .graphics_settings
+                        .resize_debounce
+                        .is_none_or(|debounce| requested_at.elapsed() >= debounce)
+                    {
+                        self.pending_resize = None;
+                        self.resize(size);
+                    }
                 }
 
-                if self.graphics_settings.frametime_or_vsync.is_none()
-                    || self.next_frame_time <= Instant::now()
+                if self.occluded || !(self.has_focus || self.graphics_settings.render_without_focus)
                 {
-                    if let Err(error) = self.draw() {
+                    // Sleep until visible and focused again, which requests a redraw.
+                    event_loop.set_control_flow(ControlFlow::Wait);
+                    break 'block;
+                }
+
+                if self.graphics_settings.frametime_or_vsync.is_none() || self.can_draw() {
+                    if self
+                        .graphics_context
+                        .as_ref()
+                        .is_some_and(GraphicsContext::is_device_lost)
+                    {
+                        if let Err(error) = self.recreate_graphics_context() {
+                            error!("Failed to recover from losing the device: {error:?}");
+                            self.exit(event_loop);
+                            break 'block;
+                        }
+                    }
+
+                    let frame_start = Instant::now();
+                    #[cfg(feature = "gamepad")]
+                    self.poll_gamepads();
+                    self.handle_hotkeys();
+                    let (clear_color, frametime) = (
+                        self.graphics_settings.clear_color,
+                        self.graphics_settings.frametime_or_vsync,
+                    );
+                    self.advance();
+                    self.apply_settings_changes(clear_color, frametime);
+                    if std::mem::take(&mut self.exit_requested) {
+                        self.request_exit(event_loop);
+                        if event_loop.exiting() {
+                            break 'block;
+                        }
+                    }
+
+                    if let Err(error) = self.draw().or_else(|error| self.recover_from(error)) {
                         error!("Draw Error: {error:?}");
+                        self.exit(event_loop);
+                        break 'block;
                     }
+                    self.handle_capture();
+                    self.check_frame_budget(frame_start.elapsed());
+                    self.input.end_frame();
 
                     if let Some(frametime) = self.graphics_settings.frametime_or_vsync {
-                        self.next_frame_time = Instant::now() + frametime;
+                        let now = Instant::now();
+                        self.next_frame_time = if self.graphics_settings.refresh_rate_pacing {
+                            // Keep to the display's cadence, unless a slow frame fell behind it.
+                            (self.next_frame_time + frametime).max(now)
+                        } else {
+                            now + frametime
+                        };
+                    }
+
+                    for (window, _) in self.windows.values() {
+                        window.request_redraw();
                     }
                 }
 
-                self.window.as_ref().unwrap().request_redraw();
-            }
-            WindowEvent::Resized(_) => {
-                if let Some(gc) = &mut self.graphics_context {
-                    gc.reconfigure_surface(self.window.as_ref().unwrap(), &self.graphics_settings);
-                }
-            }
-            WindowEvent::KeyboardInput {
-                event:
-                    KeyEvent {
-                        physical_key: key,
-                        state: ElementState::Pressed,
-                        ..
-                    },
-                ..
-            } => {
-                if let PhysicalKey::Code(kc) = key {
-                    match kc {
-                        KeyCode::KeyF => debug!(
-                            "Framerate: {:.3} fps",
-                            1.0 / self.performance_stats.get_frame_time().as_secs_f64()
-                        ),
-                        _ => {}
+                match self.graphics_settings.frametime_or_vsync {
+                    // Sleep until the next frame is due instead of spinning on the clock;
+                    // `about_to_wait` requests the redraw once it is.
+                    Some(_) => {
+                        event_loop.set_control_flow(ControlFlow::WaitUntil(self.next_frame_time))
+                    }
+                    None => {
+                        event_loop.set_control_flow(ControlFlow::Poll);
+                        self.window.as_ref().unwrap().request_redraw();
                     }
                 }
             }
-            WindowEvent::ModifiersChanged(modifiers) => {
-                // Keyboard Modifiers
+            WindowEvent::Resized(size) => {
+                self.size = size;
+                if let Some(constrained) = self.window_settings.constrain_aspect(size) {
+                    // Another `Resized` follows once the window manager applies it.
+                    let _ = self
+                        .window
+                        .as_ref()
+                        .unwrap()
+                        .request_inner_size(constrained);
+                }
+
+                if size == self.surface_size {
+                    // Nothing to do, and a newer size replaces any pending one.
+                    self.pending_resize = None;
+                } else {
+                    self.pending_resize = Some((size, Instant::now()));
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+            // The orbit camera zooms with the scroll wheel itself.
+            WindowEvent::MouseWheel { delta, .. } if self.active_camera != ActiveCamera::Orbit => {
+                let lines = input::scroll_lines(delta);
+                self.camera
+                    .set_target_fov(self.camera.target_fov() - lines * FOV_STEP);
             }
             _ => {}
         }
     }
 }
+
+/// Splits `accumulated` time into whole fixed `step`s, returning how many to run (at most
+/// `max_steps`), the time skipped by the steps beyond that, and the remainder of a step left over.
+fn fixed_steps(accumulated: Duration, step: Duration, max_steps: u32) -> (u32, Duration, Duration) {
+    let (step_nanos, nanos) = (step.as_nanos(), accumulated.as_nanos());
+    let steps = nanos / step_nanos;
+    let run = steps.min(max_steps as u128) as u32;
+    let skipped = step * (steps - run as u128) as u32;
+    let remainder = Duration::from_nanos((nanos % step_nanos) as u64);
+    (run, skipped, remainder)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn runs_whole_steps_and_carries_the_remainder() {
+        assert_eq!(fixed_steps(ms(35), ms(10), 8), (3, ms(0), ms(5)));
+        assert_eq!(fixed_steps(ms(10), ms(10), 8), (1, ms(0), ms(0)));
+        assert_eq!(fixed_steps(ms(9), ms(10), 8), (0, ms(0), ms(9)));
+    }
+
+    #[test]
+    fn large_deltas_clamp_to_max_steps() {
+        assert_eq!(fixed_steps(ms(255), ms(10), 8), (8, ms(170), ms(5)));
+        assert_eq!(fixed_steps(ms(25), ms(10), 0), (0, ms(20), ms(5)));
+    }
+
+    #[test]
+    fn long_stalls_run_at_most_max_updates_per_frame() {
+        let updates = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counter = updates.clone();
+        let settings = GraphicsSettings::default()
+            .with_update_rate(60.0)
+            .with_max_updates_per_frame(4);
+        let mut engine = Engine::new(Default::default())
+            .with_graphics_settings(settings)
+            .with_update(move |_, _| counter.set(counter.get() + 1));
+
+        // A 5 second stall, e.g. from a breakpoint.
+        engine.last_update = Instant::now() - Duration::from_secs(5);
+        engine.advance();
+
+        assert_eq!(updates.get(), 4);
+        // Everything past the capped steps is dropped rather than caught up on later.
+        assert!(engine.update_accumulator < Duration::from_secs_f64(1.0 / 60.0));
+        // So the next frame runs at most the step that remainder grows into.
+        engine.advance();
+        assert!(updates.get() <= 5);
+    }
+
+    #[test]
+    fn recreating_the_graphics_context_keeps_the_settings() {
+        let settings = GraphicsSettings::default()
+            .with_framerate(144.0)
+            .with_clear_color(0.1, 0.2, 0.3, 1.0)
+            .with_update_rate(30.0)
+            .with_max_updates_per_frame(3)
+            .with_fxaa(true);
+        let mut engine = Engine::new(Default::default()).with_graphics_settings(settings.clone());
+
+        // Without a window there's nothing to rebuild.
+        engine.recreate_graphics_context().unwrap();
+        assert_eq!(engine.graphics_settings, settings);
+
+        // Rebuild offscreen instead, twice, as after losing the device; skipped without a GPU.
+        for _ in 0..2 {
+            match engine.rebuild_graphics_context(|settings| {
+                GraphicsContext::new_headless(settings, 64, 64).block_on()
+            }) {
+                Err(EngineError::NoCompatibleAdapter) => return,
+                result => result.unwrap(),
+            }
+            assert_eq!(engine.graphics_settings, settings);
+            let gc = engine.graphics_context.as_ref().unwrap();
+            assert_eq!(gc.clear_color(), settings.clear_color);
+        }
+    }
+
+    #[test]
+    fn ctrl_q_quits() {
+        use winit::keyboard::{KeyCode, ModifiersState};
+
+        let mut engine = Engine::new(Default::default());
+        engine.input.press_key(KeyCode::KeyQ);
+        engine.handle_hotkeys();
+        assert!(!engine.exit_requested);
+
+        engine.input.end_frame();
+        engine.input.release_key(KeyCode::KeyQ);
+        engine.input.set_modifiers(ModifiersState::CONTROL);
+        engine.input.press_key(KeyCode::KeyQ);
+        engine.handle_hotkeys();
+        assert!(engine.exit_requested);
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+/// A compute shader dispatch recorded every frame before rendering.
+#[derive(Debug, Clone)]
+pub struct ComputeDispatch {
+    pub pipeline: Arc<wgpu::ComputePipeline>,
+    /// Bound in order starting at group 0
+    pub bind_groups: Vec<Arc<wgpu::BindGroup>>,
+    pub workgroups: [u32; 3],
+}
+
+/// Records every dispatch into a single compute pass on `encoder`.
+pub fn record<'d>(
+    encoder: &mut wgpu::CommandEncoder,
+    dispatches: impl IntoIterator<Item = &'d ComputeDispatch>,
+) {
+    let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+    for dispatch in dispatches {
+        pass.set_pipeline(&dispatch.pipeline);
+        for (i, bind_group) in dispatch.bind_groups.iter().enumerate() {
+            pass.set_bind_group(i as u32, bind_group, &[]);
+        }
+        let [x, y, z] = dispatch.workgroups;
+        pass.dispatch_workgroups(x, y, z);
+    }
+}
@@ -0,0 +1,77 @@
+use std::ops::Range;
+
+use log::*;
+
+use super::mesh::Mesh;
+use super::{GraphicsContext, ShaderHandle};
+
+/// Handed to the engine's render callback to record draws into the main pass, after the
+/// built-in objects. The frame uniforms are already bound to group 0.
+pub struct DrawContext<'a, 'p> {
+    graphics: &'a GraphicsContext<'a>,
+    rp: &'a mut wgpu::RenderPass<'p>,
+}
+
+impl<'a, 'p> DrawContext<'a, 'p> {
+    pub(crate) fn new(graphics: &'a GraphicsContext<'a>, rp: &'a mut wgpu::RenderPass<'p>) -> Self {
+        Self { graphics, rp }
+    }
+
+    pub fn graphics(&self) -> &GraphicsContext<'a> {
+        self.graphics
+    }
+
+    /// The underlying pass, for anything the helpers don't cover
+    pub fn render_pass(&mut self) -> &mut wgpu::RenderPass<'p> {
+        self.rp
+    }
+
+    /// Switches to the pipeline of the most recently loaded shader named `name`. Returns
+    /// whether it exists; if not, the current pipeline is kept and a warning logged.
+    pub fn set_pipeline(&mut self, name: &str) -> bool {
+        match self.graphics.get_pipeline(name) {
+            Some(pipeline) => {
+                self.rp.set_pipeline(pipeline);
+                true
+            }
+            None => {
+                warn!("No shader named '{name}' to draw with.");
+                false
+            }
+        }
+    }
+
+    /// Switches to `shader`'s pipeline, returning whether it's still loaded.
+    pub fn set_shader(&mut self, shader: ShaderHandle) -> bool {
+        match self.graphics.shader(shader) {
+            Some(shader) => {
+                self.rp.set_pipeline(&shader.pipeline);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_bind_group(&mut self, index: u32, bind_group: &wgpu::BindGroup) {
+        self.rp.set_bind_group(index, bind_group, &[]);
+    }
+
+    /// Draws all of `mesh` with the current pipeline.
+    pub fn draw_mesh(&mut self, mesh: &Mesh) {
+        self.draw_mesh_instanced(mesh, 0..1);
+    }
+
+    /// Draws `instances` of `mesh` with the current pipeline, e.g. with an instance buffer
+    /// bound to slot 1 through [`Self::render_pass`].
+    pub fn draw_mesh_instanced(&mut self, mesh: &Mesh, instances: Range<u32>) {
+        self.rp.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.rp
+            .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        self.rp.draw_indexed(0..mesh.index_count, 0, instances);
+    }
+
+    /// Draws `vertices` without any vertex buffers, e.g. for vertices generated in the shader.
+    pub fn draw(&mut self, vertices: Range<u32>) {
+        self.rp.draw(vertices, 0..1);
+    }
+}
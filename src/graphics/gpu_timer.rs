@@ -0,0 +1,115 @@
+use std::{
+    sync::mpsc::{channel, Receiver},
+    time::Duration,
+};
+
+/// Size of the two resolved `u64` timestamps
+const TIMESTAMPS_SIZE: u64 = 2 * std::mem::size_of::<u64>() as u64;
+
+/// Measures how long the GPU spends in a render pass using timestamp queries.
+///
+/// Results are read back without stalling, so they trail the frame they were recorded in by
+/// a frame or two. Requires `TIMESTAMP_QUERY`.
+#[derive(Debug)]
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    /// Where the queries are resolved to each frame
+    resolve_buffer: wgpu::Buffer,
+    /// Mappable copy of `resolve_buffer`, only refreshed once the previous copy was read
+    readback_buffer: wgpu::Buffer,
+    /// Whether `readback_buffer` was written this frame and should be mapped
+    copied: bool,
+    /// Notified once `readback_buffer` is mapped
+    mapping: Option<Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    /// Nanoseconds per timestamp tick
+    period: f32,
+    last_pass_time: Option<Duration>,
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        Self {
+            query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: None,
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            }),
+            resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: TIMESTAMPS_SIZE,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            }),
+            readback_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: TIMESTAMPS_SIZE,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+            copied: false,
+            mapping: None,
+            period: queue.get_timestamp_period(),
+            last_pass_time: None,
+        }
+    }
+
+    /// Writes to pass into the `RenderPassDescriptor` of the pass being timed
+    pub fn timestamp_writes(&self) -> wgpu::RenderPassTimestampWrites<'_> {
+        wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(0),
+            end_of_pass_write_index: Some(1),
+        }
+    }
+
+    /// Resolves the timestamps after the timed pass, copying them out if the last copy was read.
+    pub fn resolve(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+
+        if self.mapping.is_none() {
+            encoder.copy_buffer_to_buffer(
+                &self.resolve_buffer,
+                0,
+                &self.readback_buffer,
+                0,
+                TIMESTAMPS_SIZE,
+            );
+            self.copied = true;
+        }
+    }
+
+    /// Starts reading back this frame's timestamps, if copied, and picks up any finished
+    /// readback. Call after submitting.
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if std::mem::take(&mut self.copied) {
+            let (sender, receiver) = channel();
+            self.readback_buffer
+                .slice(..)
+                .map_async(wgpu::MapMode::Read, move |result| {
+                    let _ = sender.send(result);
+                });
+            self.mapping = Some(receiver);
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+        let Some(Ok(result)) = self.mapping.as_ref().map(Receiver::try_recv) else {
+            return;
+        };
+        self.mapping = None;
+
+        if result.is_ok() {
+            let timestamps: [u64; 2] =
+                bytemuck::pod_read_unaligned(&self.readback_buffer.slice(..).get_mapped_range());
+            let ticks = timestamps[1].saturating_sub(timestamps[0]);
+            self.last_pass_time = Some(Duration::from_nanos(
+                (ticks as f64 * self.period as f64) as u64,
+            ));
+            self.readback_buffer.unmap();
+        }
+    }
+
+    /// GPU time of the most recently read back pass
+    pub fn last_pass_time(&self) -> Option<Duration> {
+        self.last_pass_time
+    }
+}
@@ -0,0 +1,157 @@
+use std::mem::{offset_of, size_of};
+use std::sync::Arc;
+
+use wgpu::util::DeviceExt as _;
+
+/// Per-instance transform and color tint, matching `InstanceInput` in `shaders/instanced.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    /// Column-major model to world transform
+    pub model: [[f32; 4]; 4],
+    /// Linear RGBA, multiplied with the vertex color
+    pub color: [f32; 4],
+}
+
+impl Instance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(Instance, model) as u64,
+            shader_location: 5,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(Instance, model) as u64 + 16,
+            shader_location: 6,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(Instance, model) as u64 + 32,
+            shader_location: 7,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(Instance, model) as u64 + 48,
+            shader_location: 8,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(Instance, color) as u64,
+            shader_location: 9,
+        },
+    ];
+
+    pub fn new(model: glam::Mat4, color: [f32; 4]) -> Self {
+        Self {
+            model: model.to_cols_array_2d(),
+            color,
+        }
+    }
+
+    /// The buffer layout to build pipelines drawing `Instance` buffers with, stepped per instance.
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<Instance>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Per-instance data bound at vertex buffer slot 1, grown as needed when rewritten.
+#[derive(Debug, Clone)]
+pub struct InstanceBuffer {
+    buffer: Arc<wgpu::Buffer>,
+    /// Number of instances written
+    count: u32,
+}
+
+impl InstanceBuffer {
+    pub fn new<T: bytemuck::Pod>(device: &wgpu::Device, instances: &[T]) -> Self {
+        Self {
+            buffer: Arc::new(Self::create_buffer(device, bytemuck::cast_slice(instances))),
+            count: instances.len() as u32,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, contents: &[u8]) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: None,
+            contents,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    /// Replaces the instance data, reallocating if it no longer fits. Returns whether it did, in
+    /// which case objects drawing the old [`Self::buffer`] need the new one.
+    pub fn write<T: bytemuck::Pod>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        instances: &[T],
+    ) -> bool {
+        let contents: &[u8] = bytemuck::cast_slice(instances);
+        self.count = instances.len() as u32;
+
+        if contents.len() as u64 > self.buffer.size() {
+            self.buffer = Arc::new(Self::create_buffer(device, contents));
+            true
+        } else {
+            queue.write_buffer(&self.buffer, 0, contents);
+            false
+        }
+    }
+
+    pub fn buffer(&self) -> &Arc<wgpu::Buffer> {
+        &self.buffer
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pollster::FutureExt as _;
+
+    use super::*;
+    use crate::graphics::scene::SceneObject;
+    use crate::graphics::vertex::Vertex;
+    use crate::graphics::GraphicsContext;
+    use crate::settings::GraphicsSettings;
+
+    #[test]
+    fn draws_every_instance_in_one_call() {
+        let settings = GraphicsSettings::default().with_shaders(vec![]);
+        // Skipped without a GPU.
+        let Ok(mut gc) = GraphicsContext::new_headless(&settings, 64, 64).block_on() else {
+            return;
+        };
+
+        let white = [1.0; 4];
+        let quad = gc.upload_mesh(
+            &[
+                Vertex::new([-0.5, -0.5, 0.0], white),
+                Vertex::new([0.5, -0.5, 0.0], white),
+                Vertex::new([0.5, 0.5, 0.0], white),
+                Vertex::new([-0.5, 0.5, 0.0], white),
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+        let instances: Vec<_> = (0..10_000)
+            .map(|i| {
+                let offset = glam::vec3((i % 100) as f32, (i / 100) as f32, 0.0) * 0.01;
+                Instance::new(glam::Mat4::from_translation(offset), white)
+            })
+            .collect();
+        let buffer = gc.create_instance_buffer(&instances);
+        assert_eq!(buffer.count(), 10_000);
+
+        let object = SceneObject::instanced(gc.instanced_shader(), &quad, &buffer);
+        gc.objects_mut().push(object);
+        gc.draw().unwrap();
+        assert_eq!(gc.draw_calls(), 1);
+    }
+}
@@ -0,0 +1,10 @@
+use std::sync::Arc;
+
+/// Indexed geometry uploaded to the GPU, drawn as a list of triangles.
+#[derive(Debug, Clone)]
+pub struct Mesh {
+    pub vertex_buffer: Arc<wgpu::Buffer>,
+    /// `u32` indices into `vertex_buffer`
+    pub index_buffer: Arc<wgpu::Buffer>,
+    pub index_count: u32,
+}
@@ -0,0 +1,2367 @@
+// wgpu handles aren't `Send`/`Sync` on the web, where everything runs on one thread anyway. They're
+// still shared through `Arc` there so the API is the same on every target.
+#![cfg_attr(target_arch = "wasm32", allow(clippy::arc_with_non_send_sync))]
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+    fs::read_to_string,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use log::*;
+#[cfg(not(target_arch = "wasm32"))]
+use pollster::FutureExt as _;
+use web_time::Instant;
+
+use crate::assets::{AssetEventSender, AssetReloaded};
+use crate::settings::{GraphicsSettings, Tonemap};
+use crate::util::error::{CaptureError, EngineError, ReadbackError, ShaderError, TextureError};
+
+pub mod blit;
+pub mod compute;
+pub mod draw;
+pub mod gpu_timer;
+pub mod instance;
+pub mod mesh;
+pub mod overlay;
+pub mod pass;
+pub mod readback;
+pub mod scene;
+pub mod shader_watcher;
+pub mod sprite;
+pub mod storage;
+pub mod texture;
+pub mod uniforms;
+pub mod vertex;
+
+use blit::Blitter;
+use compute::ComputeDispatch;
+use draw::DrawContext;
+use gpu_timer::GpuTimer;
+use instance::{Instance, InstanceBuffer};
+use mesh::Mesh;
+use overlay::TextOverlay;
+use pass::{Pass, PassInput, RenderTarget, RenderTargetHandle};
+use scene::SceneObject;
+use shader_watcher::ShaderWatcher;
+use sprite::SpriteBatch;
+use storage::StorageBuffer;
+use texture::Texture;
+use uniforms::FrameUniforms;
+use vertex::{TexturedVertex, Vertex};
+
+/// Shaders drawn by default, by name, along with their source embedded as a fallback for when
+/// they aren't on disk, e.g. in a distributed binary. Overridden by
+/// [`GraphicsSettings::with_shaders`].
+pub const SHADERS: &[(&str, &str)] = &[("shader", include_str!("../../shaders/shader.wgsl"))];
+/// Extended range (scRGB-style) swapchain format used for HDR output
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Format of the intermediate target the scene is drawn into with [`GraphicsSettings::with_hdr`]
+pub const HDR_SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// Format of headless frames, as read back by [`GraphicsContext::read_pixels`]
+pub const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+/// Depth-only so it can be copied back to the CPU; combined depth-stencil formats can't be
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Refers to a shader loaded into a [`GraphicsContext`], valid until it's destroyed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderHandle(usize);
+
+/// Entry points shaders are assumed to have unless told otherwise
+pub const DEFAULT_VS_ENTRY: &str = "vs_main";
+pub const DEFAULT_FS_ENTRY: &str = "fs_main";
+
+/// Where to load a shader from and how to call into it, for [`GraphicsContext::load_shader_with`].
+#[derive(Debug, Clone)]
+pub struct ShaderDescriptor {
+    pub path: PathBuf,
+    /// Name of the vertex entry point
+    pub vs_entry: String,
+    /// Name of the fragment entry point
+    pub fs_entry: String,
+}
+
+impl ShaderDescriptor {
+    /// Loads `path` with the [`DEFAULT_VS_ENTRY`] and [`DEFAULT_FS_ENTRY`] entry points.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            vs_entry: DEFAULT_VS_ENTRY.to_owned(),
+            fs_entry: DEFAULT_FS_ENTRY.to_owned(),
+        }
+    }
+
+    pub fn with_vs_entry(mut self, vs_entry: impl Into<String>) -> Self {
+        self.vs_entry = vs_entry.into();
+        self
+    }
+
+    pub fn with_fs_entry(mut self, fs_entry: impl Into<String>) -> Self {
+        self.fs_entry = fs_entry.into();
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Shader {
+    /// File stem of the shader's source
+    pub name: String,
+    /// Source file the shader is reloaded from when it changes; `None` for built-in shaders
+    pub path: Option<PathBuf>,
+    /// Entry points the pipelines were built with, reused when reloading
+    pub vs_entry: String,
+    pub fs_entry: String,
+    pub pipeline: wgpu::RenderPipeline,
+    /// Line-mode variant with the shader's own fragment stage, drawn instead of `pipeline` in
+    /// wireframe mode; `None` without `POLYGON_MODE_LINE`
+    pub line: Option<wgpu::RenderPipeline>,
+    /// Flat-colored line-mode variant drawn over `pipeline`; `None` without `POLYGON_MODE_LINE`
+    pub wireframe: Option<wgpu::RenderPipeline>,
+    /// Whether objects using this shader are drawn
+    pub enabled: bool,
+}
+
+/// CPU-side timings of a single frame
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameTimings {
+    /// Time spent in `get_current_texture`, i.e. waiting on vsync or the GPU
+    pub acquire: Duration,
+    /// Time spent recording and submitting the frame, excluding `acquire`
+    pub cpu: Duration,
+}
+
+/// Where frames are rendered to
+#[derive(Debug)]
+enum FrameTarget<'window> {
+    Surface(wgpu::Surface<'window>),
+    /// Headless, with `COPY_SRC` so it can be read back
+    Offscreen(wgpu::Texture),
+}
+
+#[derive(Debug)]
+pub struct GraphicsContext<'window> {
+    adapter: wgpu::Adapter,
+    target: FrameTarget<'window>,
+    /// Created once so resizing keeps the chosen format and present mode
+    config: wgpu::SurfaceConfiguration,
+    /// The supported explicitly requested present mode, kept when toggling vsync
+    present_mode_override: Option<wgpu::PresentMode>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// Indexed by `ShaderHandle`, `None` once destroyed so other handles stay valid
+    shaders: Vec<Option<Shader>>,
+    /// Handles of the loaded shaders by name
+    shader_names: HashMap<String, ShaderHandle>,
+    /// If set, only objects drawn with this shader are rendered
+    solo_shader: Option<ShaderHandle>,
+    /// Where [`SHADERS`] are loaded from, watched for changes to hot-reload them
+    shader_dir: PathBuf,
+    /// `None` if the shader directory couldn't be watched
+    shader_watcher: Option<ShaderWatcher>,
+    /// Told about every shader reloaded from disk, if set
+    asset_events: Option<AssetEventSender>,
+    vertex_color_shader: ShaderHandle,
+    textured_shader: ShaderHandle,
+    instanced_shader: ShaderHandle,
+    /// Layout of the texture bind groups bound at group 1 by the textured shader
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    /// Flat color fragment stage for wireframe pipelines; `None` without `POLYGON_MODE_LINE`
+    wireframe_module: Option<wgpu::ShaderModule>,
+    /// Whether geometry is drawn again in wireframe on top of itself
+    wireframe_overlay: bool,
+    /// Whether geometry is drawn as lines instead of filled
+    wireframe: bool,
+    /// Faces culled by shader pipelines
+    cull_mode: Option<wgpu::Face>,
+    front_face: wgpu::FrontFace,
+    /// Draw calls issued each frame, sorted by state before drawing
+    objects: Vec<SceneObject>,
+    /// Draw calls `objects` took in the last frame
+    draw_calls: u32,
+    /// Compute work run each frame before drawing
+    compute_dispatches: Vec<ComputeDispatch>,
+    /// Compute work run once, in the next frame after the every-frame work
+    pending_dispatches: Vec<ComputeDispatch>,
+    /// Textured quads drawn over the objects, cleared each frame
+    sprite_batch: SpriteBatch,
+    /// Fullscreen passes run in order after the scene
+    passes: Vec<Pass>,
+    /// Offscreen targets the passes render into
+    render_targets: Vec<RenderTarget>,
+    /// What the scene is drawn into instead of the frame while there are passes, created on first
+    /// use
+    scene_target: Option<RenderTarget>,
+    /// Samples pass inputs
+    pass_sampler: wgpu::Sampler,
+    /// Anti-aliases the frame from `fxaa_target`, if FXAA is enabled
+    fxaa_pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    /// What the scene and passes draw into instead of the frame while FXAA is enabled
+    fxaa_target: Option<RenderTarget>,
+    /// Whether the next frame drawn is captured
+    capture_requested: bool,
+    /// What a captured frame is composited into before being copied to the frame and read back,
+    /// created on first use
+    capture_target: Option<RenderTarget>,
+    /// Copies `capture_target` to the frame
+    capture_pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    /// The last captured frame, until taken
+    capture: Option<Result<image::RgbaImage, CaptureError>>,
+    /// Format scene pipelines draw in; [`HDR_SCENE_FORMAT`] with HDR rendering, otherwise the
+    /// surface's
+    scene_format: wgpu::TextureFormat,
+    tonemap: Tonemap,
+    /// Maps `hdr_target` into the displayable range, with HDR rendering
+    tonemap_pipeline: Option<Arc<wgpu::RenderPipeline>>,
+    /// What the scene is drawn into with HDR rendering, before tonemapping
+    hdr_target: Option<RenderTarget>,
+    /// Whether compute work is submitted separately ahead of rendering
+    async_compute: bool,
+    /// Frames drawn beyond this many unfinished ones first wait for the oldest, if set
+    max_frames_in_flight: Option<u32>,
+    /// Submissions of the frames that may still be in flight, oldest first
+    frames_in_flight: VecDeque<wgpu::SubmissionIndex>,
+    /// Whether GPU objects are given labels, see [`Self::label`]
+    debug_labels: bool,
+    /// Whether the swapchain is extended range, in which case tonemapping should be skipped
+    hdr_output: bool,
+
+    /// Number of samples per pixel actually in use; may be lower than requested
+    sample_count: u32,
+    /// Multisampled color target resolved into the swapchain texture, if `sample_count > 1`
+    msaa_view: Option<wgpu::TextureView>,
+    /// Depth buffer, multisampled alongside the color target
+    depth_texture: wgpu::Texture,
+    depth_view: wgpu::TextureView,
+
+    clear_color: wgpu::Color,
+
+    /// Whether textures that fail to load are replaced by [`Texture::missing`]
+    missing_texture_fallback: bool,
+
+    /// Times the render pass on the GPU, if enabled and supported
+    gpu_timer: Option<GpuTimer>,
+
+    /// Holds the [`FrameUniforms`], rewritten before each frame
+    frame_uniforms: wgpu::Buffer,
+    frame_bind_group_layout: wgpu::BindGroupLayout,
+    frame_bind_group: wgpu::BindGroup,
+    /// When the context was created, for [`FrameUniforms::time`]
+    start_time: Instant,
+    /// Uploaded as [`FrameUniforms::view_proj`]
+    view_projection: glam::Mat4,
+
+    /// Bind group layouts by their entries, so identical ones are shared
+    bind_group_layouts: Mutex<HashMap<Vec<wgpu::BindGroupLayoutEntry>, Arc<wgpu::BindGroupLayout>>>,
+    /// Pipeline layouts by the bind group layouts they're made of, shared between pipelines
+    pipeline_layouts: Mutex<PipelineLayoutCache>,
+
+    /// Set once the device is lost, e.g. by a driver reset, after which it can't be used anymore
+    device_lost: Arc<AtomicBool>,
+
+    /// Created on first use, and kept around while hidden as loading fonts is slow
+    text_overlay: Option<TextOverlay>,
+    text_overlay_visible: bool,
+}
+
+type PipelineLayoutCache = HashMap<Vec<wgpu::Id<wgpu::BindGroupLayout>>, Arc<wgpu::PipelineLayout>>;
+
+impl<'a> GraphicsContext<'a> {
+    /// Creates a new graphics context for the `window`.
+    pub async fn new(
+        settings: &GraphicsSettings,
+        window: Arc<winit::window::Window>,
+    ) -> Result<Self, EngineError> {
+        let instance = Self::create_instance(settings);
+        let surface = instance.create_surface(window.clone())?;
+        let (adapter, device, queue, gpu_timing) =
+            Self::request_device(&instance, settings, Some(&surface)).await?;
+
+        let format = Self::choose_format(&surface, &adapter, settings);
+        let config = Self::configure_surface(
+            &surface,
+            &adapter,
+            &device,
+            window.as_ref(),
+            settings,
+            format,
+        );
+
+        Ok(Self::init(
+            settings,
+            adapter,
+            device,
+            queue,
+            FrameTarget::Surface(surface),
+            config,
+            gpu_timing,
+        ))
+    }
+
+    /// Creates a graphics context without a window, rendering `width` by `height` frames into a
+    /// [`OFFSCREEN_FORMAT`] texture that can be read back with [`Self::read_pixels`].
+    pub async fn new_headless(
+        settings: &GraphicsSettings,
+        width: u32,
+        height: u32,
+    ) -> Result<GraphicsContext<'static>, EngineError> {
+        let instance = Self::create_instance(settings);
+        let (adapter, device, queue, gpu_timing) =
+            Self::request_device(&instance, settings, None).await?;
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: OFFSCREEN_FORMAT,
+            width: width.max(1),
+            height: height.max(1),
+            // Unused without a surface.
+            present_mode: wgpu::PresentMode::Fifo,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![],
+        };
+        let texture = Self::create_offscreen_texture(&device, &config);
+
+        Ok(GraphicsContext::init(
+            settings,
+            adapter,
+            device,
+            queue,
+            FrameTarget::Offscreen(texture),
+            config,
+            gpu_timing,
+        ))
+    }
+
+    fn create_instance(settings: &GraphicsSettings) -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends: settings.backends,
+            ..Default::default()
+        })
+    }
+
+    /// Picks an adapter, compatible with `surface` if given, and creates the device, returning
+    /// whether GPU timing ended up enabled.
+    async fn request_device(
+        instance: &wgpu::Instance,
+        settings: &GraphicsSettings,
+        surface: Option<&wgpu::Surface<'_>>,
+    ) -> Result<(wgpu::Adapter, wgpu::Device, wgpu::Queue, bool), EngineError> {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptionsBase {
+                power_preference: settings.power_preference,
+                force_fallback_adapter: false,
+                compatible_surface: surface, // Request an adapter compatible with our surface, if any
+            })
+            .await
+            .ok_or(EngineError::NoCompatibleAdapter)?;
+
+        let info = adapter.get_info();
+        info!(
+            "Using {} ({:?}, {:?}, driver {} {}).",
+            info.name, info.backend, info.device_type, info.driver, info.driver_info
+        );
+
+        // Optional features; sample counts beyond the WebGPU-guaranteed 1x and 4x and the
+        // wireframe rendering respectively.
+        let mut required_features = adapter.features()
+            & (wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                | wgpu::Features::POLYGON_MODE_LINE);
+        let gpu_timing =
+            settings.gpu_timing && adapter.features().contains(wgpu::Features::TIMESTAMP_QUERY);
+        if gpu_timing {
+            required_features |= wgpu::Features::TIMESTAMP_QUERY;
+        } else if settings.gpu_timing {
+            warn!("GPU timing is unsupported by this adapter, disabling it.");
+        }
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: settings.debug_labels.then_some("yagve device"),
+                    required_features,
+                    required_limits: wgpu::Limits::downlevel_webgl2_defaults()
+                        .using_alignment(adapter.limits()),
+                    memory_hints: wgpu::MemoryHints::MemoryUsage,
+                },
+                settings.api_trace_dir.as_deref(),
+            )
+            .await?;
+
+        Ok((adapter, device, queue, gpu_timing))
+    }
+
+    /// Sets up everything past the device that's shared between windowed and headless contexts.
+    fn init(
+        settings: &GraphicsSettings,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        target: FrameTarget<'a>,
+        config: wgpu::SurfaceConfiguration,
+        gpu_timing: bool,
+    ) -> Self {
+        let format = config.format;
+        let scene_format = Self::pick_scene_format(&adapter, settings, format);
+        let sample_count =
+            Self::supported_sample_count(&adapter, &device, scene_format, settings.msaa_samples);
+        let msaa_view = Self::create_msaa_view(&device, &config, scene_format, sample_count);
+        let (depth_texture, depth_view) =
+            Self::create_depth_texture(&device, &config, sample_count);
+
+        let frame_uniforms = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<FrameUniforms>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let frame_bind_group_layout = FrameUniforms::bind_group_layout(&device);
+        let frame_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &frame_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frame_uniforms.as_entire_binding(),
+            }],
+        });
+
+        let texture_bind_group_layout = Texture::bind_group_layout(&device);
+        let device_lost = Arc::new(AtomicBool::new(false));
+        device.set_device_lost_callback({
+            let device_lost = device_lost.clone();
+            move |reason, message| {
+                // The device is dropped along with the context, which isn't an error.
+                if matches!(
+                    reason,
+                    wgpu::DeviceLostReason::Unknown | wgpu::DeviceLostReason::DeviceInvalid
+                ) {
+                    error!("Lost the device ({reason:?}): {message}");
+                    device_lost.store(true, Ordering::Relaxed);
+                }
+            }
+        });
+
+        let pass_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let sprite_batch = SpriteBatch::new(
+            &device,
+            scene_format,
+            &frame_bind_group_layout,
+            &texture_bind_group_layout,
+        );
+        let shader_dir = Self::resolve_shader_dir(&settings.shader_dir);
+
+        let mut ctx = Self {
+            adapter,
+            target,
+            present_mode_override: settings.present_mode.map(|_| config.present_mode),
+            config,
+            device,
+            queue,
+            shaders: vec![],
+            shader_names: HashMap::new(),
+            solo_shader: None,
+            shader_watcher: ShaderWatcher::new(&shader_dir)
+                .inspect_err(|error| warn!("Shader hot-reloading is unavailable: {error}"))
+                .ok(),
+            shader_dir,
+            asset_events: None,
+            vertex_color_shader: ShaderHandle(0),
+            textured_shader: ShaderHandle(0),
+            instanced_shader: ShaderHandle(0),
+            texture_bind_group_layout,
+            wireframe_module: None,
+            wireframe_overlay: false,
+            wireframe: false,
+            cull_mode: settings.cull_mode,
+            front_face: settings.front_face,
+            objects: vec![],
+            draw_calls: 0,
+            compute_dispatches: vec![],
+            pending_dispatches: vec![],
+            async_compute: settings.async_compute,
+            debug_labels: settings.debug_labels,
+            // The field is public, so it may not have gone through the builder's clamp.
+            max_frames_in_flight: settings.max_frames_in_flight.map(|frames| frames.max(1)),
+            frames_in_flight: VecDeque::new(),
+            hdr_output: format == HDR_FORMAT,
+            sample_count,
+            msaa_view,
+            depth_texture,
+            depth_view,
+            clear_color: settings.clear_color,
+            missing_texture_fallback: settings.missing_texture_fallback,
+            gpu_timer: None,
+            frame_uniforms,
+            view_projection: glam::Mat4::IDENTITY,
+            frame_bind_group_layout,
+            frame_bind_group,
+            start_time: Instant::now(),
+            text_overlay: None,
+            text_overlay_visible: false,
+            passes: vec![],
+            render_targets: vec![],
+            scene_target: None,
+            pass_sampler,
+            fxaa_pipeline: None,
+            fxaa_target: None,
+            capture_requested: false,
+            capture_target: None,
+            capture_pipeline: None,
+            capture: None,
+            scene_format,
+            tonemap: settings.tonemap,
+            tonemap_pipeline: None,
+            hdr_target: None,
+            bind_group_layouts: Default::default(),
+            pipeline_layouts: Default::default(),
+            device_lost,
+            sprite_batch,
+        };
+        if gpu_timing {
+            ctx.gpu_timer = Some(GpuTimer::new(&ctx.device, &ctx.queue));
+        }
+
+        if ctx
+            .device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            ctx.wireframe_module = Some(ctx.device.create_shader_module(
+                wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                        "../../shaders/wireframe.wgsl"
+                    ))),
+                },
+            ));
+        }
+
+        ctx.vertex_color_shader = ctx
+            .create_shader(
+                "vertex_color".to_owned(),
+                include_str!("../../shaders/vertex_color.wgsl"),
+                &[Vertex::desc()],
+            )
+            .expect("Built-in vertex color shader failed to compile.");
+        let textured = ctx
+            .build_shader(
+                "textured".to_owned(),
+                include_str!("../../shaders/textured.wgsl"),
+                (DEFAULT_VS_ENTRY, DEFAULT_FS_ENTRY),
+                &[TexturedVertex::desc()],
+                Some(&ctx.texture_bind_group_layout),
+            )
+            .expect("Built-in textured shader failed to compile.");
+        ctx.textured_shader = ctx.register_shader(textured);
+        ctx.instanced_shader = ctx
+            .create_shader(
+                "instanced".to_owned(),
+                include_str!("../../shaders/instanced.wgsl"),
+                &[Vertex::desc(), Instance::desc()],
+            )
+            .expect("Built-in instanced shader failed to compile.");
+
+        if settings.fxaa {
+            ctx.set_fxaa(true);
+        }
+        if ctx.scene_format != ctx.config.format {
+            ctx.hdr_target = Some(RenderTarget::new(
+                &ctx.device,
+                ctx.config.width,
+                ctx.config.height,
+                ctx.scene_format,
+            ));
+            ctx.set_tonemap(settings.tonemap);
+        }
+
+        // Show something other than uninitialized garbage while the shaders load.
+        ctx.present_splash(settings);
+
+        for shader in &settings.shaders {
+            let path = ctx.shader_dir.join(format!("{shader}.wgsl"));
+            let embedded = SHADERS
+                .iter()
+                .find(|(name, _)| name == shader)
+                .map(|&(_, source)| source);
+            let result = match embedded {
+                Some(embedded) if !path.is_file() => {
+                    debug!("Shader '{shader}' isn't on disk, using the embedded one.");
+                    ctx.load_embedded_shader(shader, embedded)
+                }
+                _ => ctx.load_shader(path),
+            };
+
+            // A broken shader shouldn't take the rest down with it.
+            match result {
+                Ok(handle) => ctx.objects.push(SceneObject::fullscreen_triangle(handle)),
+                Err(error) => error!("Failed to load shader '{shader}', skipping it: {error:?}"),
+            }
+        }
+
+        ctx
+    }
+
+    /// Resolves a relative shader directory against the working directory, or failing that the
+    /// executable's directory, so the engine can be launched from anywhere.
+    fn resolve_shader_dir(shader_dir: &Path) -> PathBuf {
+        let next_to_exe = std::env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join(shader_dir)));
+        let resolved = match next_to_exe {
+            Some(next_to_exe) if !shader_dir.is_dir() && next_to_exe.is_dir() => next_to_exe,
+            _ => shader_dir.to_owned(),
+        };
+
+        match resolved.canonicalize() {
+            Ok(absolute) => {
+                info!("Loading shaders from '{}'.", absolute.display());
+                absolute
+            }
+            Err(error) => {
+                warn!(
+                    "Shader directory '{}' is unavailable: {error}",
+                    resolved.display()
+                );
+                resolved
+            }
+        }
+    }
+
+    /// Picks the swapchain format, preferring [`HDR_FORMAT`] if HDR output is requested and the
+    /// surface supports it, and otherwise an sRGB or linear one as requested.
+    fn choose_format(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        settings: &GraphicsSettings,
+    ) -> wgpu::TextureFormat {
+        let formats = surface.get_capabilities(adapter).formats;
+
+        if settings.hdr_output {
+            if formats.contains(&HDR_FORMAT) {
+                info!("Using HDR output with {HDR_FORMAT:?}.");
+                return HDR_FORMAT;
+            }
+            info!("HDR output is unsupported by this display or backend, falling back to SDR.");
+        }
+
+        let format = formats
+            .iter()
+            .copied()
+            .find(|format| format.is_srgb() == settings.srgb)
+            .unwrap_or_else(|| {
+                warn!(
+                    "No {} surface format is available, using {:?}.",
+                    if settings.srgb { "sRGB" } else { "linear" },
+                    formats[0]
+                );
+                formats[0]
+            });
+        info!("Using surface format {format:?}.");
+        format
+    }
+
+    fn configure_surface(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        window: &winit::window::Window,
+        settings: &GraphicsSettings,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::SurfaceConfiguration {
+        let (width, height) = {
+            let size = window.inner_size();
+            (size.width.max(1), size.height.max(1))
+        };
+
+        let mut config = surface.get_default_config(adapter, width, height).unwrap();
+        config.format = format;
+        // Set the initial graphics settings.
+        config.present_mode = match settings.present_mode {
+            Some(present_mode) => Self::supported_present_mode(surface, adapter, present_mode),
+            None => Self::vsync_present_mode(settings.frametime_or_vsync.is_none()),
+        };
+
+        surface.configure(device, &config);
+        config
+    }
+
+    /// Resizes the surface and the render targets alongside it, e.g. when the window resizes.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width.max(1);
+        self.config.height = height.max(1);
+        match &mut self.target {
+            FrameTarget::Surface(surface) => surface.configure(&self.device, &self.config),
+            FrameTarget::Offscreen(texture) => {
+                *texture = Self::create_offscreen_texture(&self.device, &self.config)
+            }
+        }
+
+        self.msaa_view = Self::create_msaa_view(
+            &self.device,
+            &self.config,
+            self.scene_format,
+            self.sample_count,
+        );
+        (self.depth_texture, self.depth_view) =
+            Self::create_depth_texture(&self.device, &self.config, self.sample_count);
+
+        let (width, height) = (self.config.width, self.config.height);
+        for target in self
+            .render_targets
+            .iter_mut()
+            .chain(&mut self.scene_target)
+            .chain(&mut self.fxaa_target)
+            .chain(&mut self.hdr_target)
+            .chain(&mut self.capture_target)
+        {
+            *target = RenderTarget::new(&self.device, width, height, target.format());
+        }
+    }
+
+    /// `requested` if the surface supports it, otherwise `Fifo` which is always supported
+    fn supported_present_mode(
+        surface: &wgpu::Surface,
+        adapter: &wgpu::Adapter,
+        requested: wgpu::PresentMode,
+    ) -> wgpu::PresentMode {
+        use wgpu::PresentMode;
+
+        let supported = matches!(requested, PresentMode::AutoVsync | PresentMode::AutoNoVsync)
+            || surface
+                .get_capabilities(adapter)
+                .present_modes
+                .contains(&requested);
+        if supported {
+            requested
+        } else {
+            warn!(
+                "Present mode {requested:?} is unsupported by the surface, falling back to Fifo."
+            );
+            PresentMode::Fifo
+        }
+    }
+
+    fn vsync_present_mode(vsync: bool) -> wgpu::PresentMode {
+        if vsync {
+            wgpu::PresentMode::AutoVsync
+        } else {
+            wgpu::PresentMode::AutoNoVsync
+        }
+    }
+
+    /// Switches between presenting with and without vsync, unless a present mode was explicitly
+    /// requested.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.config.present_mode = self
+            .present_mode_override
+            .unwrap_or_else(|| Self::vsync_present_mode(vsync));
+        self.reconfigure_surface();
+    }
+
+    /// Reapplies the current configuration, e.g. after the surface was lost.
+    pub fn reconfigure_surface(&mut self) {
+        self.resize(self.config.width, self.config.height);
+    }
+
+    /// Presents a frame cleared to the splash color, with the splash texture (if any) centered
+    /// and scaled to fit.
+    fn present_splash(&self, settings: &GraphicsSettings) {
+        let FrameTarget::Surface(surface) = &self.target else {
+            return;
+        };
+        let frame = match surface.get_current_texture() {
+            Ok(frame) => frame,
+            Err(error) => {
+                warn!("Failed to present splash: {error}");
+                return;
+            }
+        };
+        let view = frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let texture =
+            settings
+                .splash_texture
+                .as_ref()
+                .and_then(|path| match self.load_texture(path) {
+                    Ok(texture) => Some(texture),
+                    Err(error) => {
+                        warn!("Failed to load splash texture: {error:?}");
+                        None
+                    }
+                });
+        let blit = texture.as_ref().map(|texture| {
+            let blitter = Blitter::new(&self.device, self.config.format);
+            let bind_group = blitter.bind(&self.device, texture);
+            (blitter, bind_group)
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(settings.splash_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+
+            if let (Some(texture), Some((blitter, bind_group))) = (&texture, &blit) {
+                let (frame_width, frame_height) =
+                    (frame.texture.width() as f32, frame.texture.height() as f32);
+                let (width, height) = (
+                    texture.texture.width() as f32,
+                    texture.texture.height() as f32,
+                );
+                let scale = (frame_width / width).min(frame_height / height);
+                let (width, height) = (width * scale, height * scale);
+
+                rp.set_viewport(
+                    (frame_width - width) / 2.0,
+                    (frame_height - height) / 2.0,
+                    width,
+                    height,
+                    0.0,
+                    1.0,
+                );
+                blitter.draw(&mut rp, bind_group);
+            }
+        }
+
+        self.queue.submit(Some(encoder.finish()));
+        frame.present();
+    }
+
+    // HEADLESS
+
+    fn create_offscreen_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+    ) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: config.usage,
+            view_formats: &[],
+        })
+    }
+
+    /// Reads back the last headless frame as tightly packed, row-major [`OFFSCREEN_FORMAT`]
+    /// pixels. Blocks until the GPU is done, and fails for windowed contexts.
+    pub fn read_pixels(&self) -> Result<Vec<u8>, ReadbackError> {
+        let FrameTarget::Offscreen(texture) = &self.target else {
+            return Err(ReadbackError::NotHeadless);
+        };
+
+        readback::read_texture(
+            &self.device,
+            &self.queue,
+            texture,
+            wgpu::TextureAspect::All,
+            4,
+        )
+    }
+
+    // CAPTURE
+
+    /// Captures the next frame drawn, as it appears on screen but without the text overlay or
+    /// UI, to be picked up with [`Self::take_capture`] once drawn.
+    pub fn request_capture(&mut self) {
+        self.capture_requested = true;
+    }
+
+    /// The frame captured by the last draw, if one was requested. Fails for extended range (HDR)
+    /// formats.
+    pub fn take_capture(&mut self) -> Option<Result<image::RgbaImage, CaptureError>> {
+        self.capture.take()
+    }
+
+    /// Gets the capture target and the pipeline copying it to the frame ready, if a capture was
+    /// requested and the format can be read back. Returns whether this frame is captured.
+    fn prepare_capture(&mut self) -> bool {
+        if !std::mem::take(&mut self.capture_requested) {
+            return false;
+        }
+        if Self::capture_bgra(self.config.format).is_none() {
+            self.capture = Some(Err(CaptureError::UnsupportedFormat(self.config.format)));
+            return false;
+        }
+
+        if self.capture_pipeline.is_none() {
+            match self.create_pass_pipeline(
+                include_str!("../../shaders/blit.wgsl"),
+                1,
+                self.config.format,
+            ) {
+                Ok(pipeline) => self.capture_pipeline = Some(pipeline),
+                Err(error) => {
+                    error!("Built-in blit shader failed to compile: {error:?}");
+                    return false;
+                }
+            }
+        }
+        if self.capture_target.is_none() {
+            self.capture_target = Some(RenderTarget::new(
+                &self.device,
+                self.config.width,
+                self.config.height,
+                self.config.format,
+            ));
+        }
+        true
+    }
+
+    /// Whether `format` is BGRA rather than RGBA, or `None` if frames in it can't be captured
+    fn capture_bgra(format: wgpu::TextureFormat) -> Option<bool> {
+        use wgpu::TextureFormat as F;
+
+        match format {
+            F::Rgba8Unorm | F::Rgba8UnormSrgb => Some(false),
+            F::Bgra8Unorm | F::Bgra8UnormSrgb => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Reads the capture target back as an image. Blocks until the GPU is done.
+    fn read_capture(&self) -> Result<image::RgbaImage, CaptureError> {
+        let target = self
+            .capture_target
+            .as_ref()
+            .expect("Captured without a capture target.");
+        let bgra = Self::capture_bgra(self.config.format)
+            .ok_or(CaptureError::UnsupportedFormat(self.config.format))?;
+
+        // `read_texture` strips the row padding the copy needs.
+        let mut pixels = readback::read_texture(
+            &self.device,
+            &self.queue,
+            target.texture(),
+            wgpu::TextureAspect::All,
+            4,
+        )?;
+        if bgra {
+            pixels
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2));
+        }
+
+        Ok(
+            image::RgbaImage::from_raw(self.config.width, self.config.height, pixels)
+                .expect("Readback returned the wrong number of pixels."),
+        )
+    }
+
+    // MULTISAMPLING
+
+    /// Returns the highest sample count `<= requested` that `format` supports, logging any downgrade.
+    fn supported_sample_count(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        requested: u32,
+    ) -> u32 {
+        let format_features = |format: wgpu::TextureFormat| {
+            if device
+                .features()
+                .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+            {
+                adapter.get_texture_format_features(format)
+            } else {
+                format.guaranteed_format_features(device.features())
+            }
+        };
+        let (color, depth) = (
+            format_features(format).flags,
+            format_features(DEPTH_FORMAT).flags,
+        );
+
+        // Without resolve support we can't get the samples back into the swapchain.
+        let can_resolve = color.contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE);
+        let supported = [16, 8, 4, 2]
+            .into_iter()
+            .find(|&count| {
+                count <= requested
+                    && can_resolve
+                    && color.sample_count_supported(count)
+                    // The depth buffer is multisampled alongside the color target.
+                    && depth.sample_count_supported(count)
+            })
+            .unwrap_or(1);
+
+        if supported != requested {
+            warn!(
+                "{requested}x MSAA is unsupported for {format:?} with {DEPTH_FORMAT:?} depth, \
+                 using {supported}x instead."
+            );
+        }
+
+        supported
+    }
+
+    fn create_msaa_view(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Option<wgpu::TextureView> {
+        if sample_count <= 1 {
+            return None;
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+
+        Some(texture.create_view(&wgpu::TextureViewDescriptor::default()))
+    }
+
+    // DEPTH
+
+    fn create_depth_texture(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        (texture, view)
+    }
+
+    /// Reads back the last frame's depth buffer as row-major, top-to-bottom linear view-space
+    /// depths, given the `near` and `far` planes of the projection used to draw it.
+    ///
+    /// Blocks until the GPU is done. Multisampled depth can't be copied, so this fails with MSAA on.
+    pub fn read_depth(&self, near: f32, far: f32) -> Result<Vec<f32>, ReadbackError> {
+        let bytes = readback::read_texture(
+            &self.device,
+            &self.queue,
+            &self.depth_texture,
+            wgpu::TextureAspect::DepthOnly,
+            4,
+        )?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|texel| linearize_depth(f32::from_le_bytes(texel.try_into().unwrap()), near, far))
+            .collect())
+    }
+
+    /// The chosen GPU and driver, e.g. for bug reports
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
+    }
+
+    /// Features enabled on the device, including the optional ones the adapter supports
+    pub fn supported_features(&self) -> wgpu::Features {
+        self.device.features()
+    }
+
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.config.format
+    }
+
+    /// Whether the device was lost, in which case the context has to be recreated
+    pub fn is_device_lost(&self) -> bool {
+        self.device_lost.load(Ordering::Relaxed)
+    }
+
+    pub fn present_mode(&self) -> wgpu::PresentMode {
+        self.config.present_mode
+    }
+
+    /// Whether the swapchain uses the extended range [`HDR_FORMAT`]
+    pub fn hdr_output(&self) -> bool {
+        self.hdr_output
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    /// GPU time of a recent render pass, or `None` if GPU timing is off or unsupported
+    pub fn gpu_pass_time(&self) -> Option<Duration> {
+        self.gpu_timer.as_ref()?.last_pass_time()
+    }
+
+    // TEXTURES
+
+    /// Loads an image file into a texture. Unless disabled in the settings, failures are logged
+    /// and replaced with a checkerboard "missing texture" rather than returned.
+    pub fn load_texture(&self, path: &Path) -> Result<Texture, TextureError> {
+        match image::open(path) {
+            Ok(image) => {
+                let image = image.to_rgba8();
+                Ok(Texture::from_rgba(
+                    &self.device,
+                    &self.queue,
+                    image.width(),
+                    image.height(),
+                    &image,
+                    wgpu::FilterMode::Linear,
+                ))
+            }
+            Err(error) if self.missing_texture_fallback => {
+                error!("Failed to load texture {}: {error}", path.display());
+                Ok(Texture::missing(&self.device, &self.queue))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Sets the camera transform shaders read from the frame uniforms, starting next frame.
+    pub fn set_view_projection(&mut self, view_projection: glam::Mat4) {
+        self.view_projection = view_projection;
+    }
+
+    /// Binds `texture` for objects drawn with the [`Self::textured_shader`].
+    pub fn texture_bind_group(&self, texture: &Texture) -> Arc<wgpu::BindGroup> {
+        Arc::new(texture.bind_group(&self.device, &self.texture_bind_group_layout))
+    }
+
+    /// `label` if debug labels are enabled
+    fn label<'l>(&self, label: &'l str) -> Option<&'l str> {
+        self.debug_labels.then_some(label)
+    }
+
+    // LAYOUTS
+
+    /// A bind group layout with `entries`, shared with every other caller asking for the same
+    /// entries so their bind groups are interchangeable.
+    pub fn bind_group_layout(
+        &self,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Arc<wgpu::BindGroupLayout> {
+        self.bind_group_layouts
+            .lock()
+            .unwrap()
+            .entry(entries.to_vec())
+            .or_insert_with(|| {
+                Arc::new(
+                    self.device
+                        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                            label: None,
+                            entries,
+                        }),
+                )
+            })
+            .clone()
+    }
+
+    /// A pipeline layout binding `bind_group_layouts` at groups 0, 1, ..., created once per
+    /// combination.
+    pub fn pipeline_layout(
+        &self,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+    ) -> Arc<wgpu::PipelineLayout> {
+        self.pipeline_layouts
+            .lock()
+            .unwrap()
+            .entry(
+                bind_group_layouts
+                    .iter()
+                    .map(|layout| layout.global_id())
+                    .collect(),
+            )
+            .or_insert_with(|| {
+                Arc::new(
+                    self.device
+                        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                            label: None,
+                            bind_group_layouts,
+                            push_constant_ranges: &[],
+                        }),
+                )
+            })
+            .clone()
+    }
+
+    // SHADERS
+
+    /// Loads a WGSL shader from `path`, named after its file stem, with `vs_main`/`fs_main`
+    /// entry points and no vertex buffers.
+    pub fn load_shader(&mut self, path: impl AsRef<Path>) -> Result<ShaderHandle, ShaderError> {
+        self.load_shader_with(ShaderDescriptor::new(path.as_ref()))
+    }
+
+    /// Loads a shader like [`Self::load_shader`], with the entry points in `descriptor`.
+    pub fn load_shader_with(
+        &mut self,
+        descriptor: ShaderDescriptor,
+    ) -> Result<ShaderHandle, ShaderError> {
+        let path = descriptor.path;
+        let source = read_to_string(&path)?;
+        let name = path.file_stem().map_or_else(
+            || path.display().to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+
+        let shader = self.build_shader(
+            name,
+            &source,
+            (&descriptor.vs_entry, &descriptor.fs_entry),
+            &[],
+            None,
+        )?;
+        let handle = self.register_shader(shader);
+        self.shaders[handle.0].as_mut().unwrap().path = Some(path);
+        Ok(handle)
+    }
+
+    /// Builds a shader from WGSL `source` compiled into the binary, e.g. with `include_str!`,
+    /// with `vs_main`/`fs_main` entry points and no vertex buffers. It isn't hot-reloaded.
+    pub fn load_embedded_shader(
+        &mut self,
+        name: &str,
+        source: &str,
+    ) -> Result<ShaderHandle, ShaderError> {
+        self.create_shader(name.to_owned(), source, &[])
+    }
+
+    /// Rebuilds the pipelines of the shader from its source file, keeping the old ones if that
+    /// fails.
+    fn reload_shader(&mut self, handle: ShaderHandle, path: &Path) -> Result<(), ShaderError> {
+        let rebuilt = self.rebuild_shader(handle, path)?;
+        self.replace_pipelines(handle, rebuilt);
+        self.send_asset_event(path);
+        Ok(())
+    }
+
+    /// Reports shader reloads to `sender`, e.g. so application code can rebuild state derived
+    /// from them.
+    pub fn set_asset_event_sender(&mut self, sender: AssetEventSender) {
+        self.asset_events = Some(sender);
+    }
+
+    fn send_asset_event(&self, path: &Path) {
+        if let Some(sender) = &self.asset_events {
+            // Nobody listening anymore isn't an error.
+            let _ = sender.send(AssetReloaded { path: path.into() });
+        }
+    }
+
+    /// Builds the shader at `handle` again from the current contents of `path`.
+    fn rebuild_shader(&self, handle: ShaderHandle, path: &Path) -> Result<Shader, ShaderError> {
+        let source = read_to_string(path)?;
+        let shader = self.shaders[handle.0].as_ref().unwrap();
+        self.build_shader(
+            shader.name.clone(),
+            &source,
+            (&shader.vs_entry, &shader.fs_entry),
+            &[],
+            None,
+        )
+    }
+
+    fn replace_pipelines(&mut self, handle: ShaderHandle, rebuilt: Shader) {
+        let shader = self.shaders[handle.0].as_mut().unwrap();
+        shader.pipeline = rebuilt.pipeline;
+        shader.line = rebuilt.line;
+        shader.wireframe = rebuilt.wireframe;
+    }
+
+    /// Reloads every shader loaded from a file, whether or not it changed. If any fails to
+    /// build, all of them keep their old pipelines and the first error is returned.
+    pub fn reload_all_shaders(&mut self) -> Result<(), ShaderError> {
+        let reloads: Vec<_> = self
+            .shaders()
+            .filter_map(|(handle, shader)| Some((handle, shader.path.clone()?)))
+            .collect();
+
+        let mut rebuilt = Vec::with_capacity(reloads.len());
+        let mut first_error = None;
+        for (handle, path) in &reloads {
+            match self.rebuild_shader(*handle, path) {
+                Ok(shader) => rebuilt.push((*handle, shader)),
+                Err(error) => {
+                    error!("Failed to reload '{}': {error:?}", path.display());
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+
+        if let Some(error) = first_error {
+            warn!(
+                "{} of {} shaders built, keeping the old ones.",
+                rebuilt.len(),
+                reloads.len()
+            );
+            return Err(error);
+        }
+
+        info!("Reloaded {} shaders.", rebuilt.len());
+        for ((handle, shader), (_, path)) in rebuilt.into_iter().zip(&reloads) {
+            self.replace_pipelines(handle, shader);
+            self.send_asset_event(path);
+        }
+        Ok(())
+    }
+
+    /// Reloads every shader whose source file changed since the last frame.
+    fn reload_changed_shaders(&mut self) {
+        let Some(changed) = self.shader_watcher.as_ref().map(ShaderWatcher::changed) else {
+            return;
+        };
+        if changed.is_empty() {
+            return;
+        }
+
+        let reloads = self
+            .shaders()
+            .filter_map(|(handle, shader)| {
+                let path = shader.path.as_ref()?;
+                changed
+                    .contains(&path.canonicalize().ok()?)
+                    .then(|| (handle, path.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        for (handle, path) in reloads {
+            let name = self.shaders[handle.0].as_ref().unwrap().name.clone();
+            match self.reload_shader(handle, &path) {
+                Ok(()) => info!("Reloaded shader '{name}'."),
+                Err(error) => {
+                    error!("Failed to reload shader '{name}', keeping the old one: {error:?}")
+                }
+            }
+        }
+    }
+
+    /// Builds and registers a shader; see [`Self::build_shader`].
+    fn create_shader(
+        &mut self,
+        name: String,
+        source: &str,
+        buffers: &[wgpu::VertexBufferLayout],
+    ) -> Result<ShaderHandle, ShaderError> {
+        let shader = self.build_shader(
+            name,
+            source,
+            (DEFAULT_VS_ENTRY, DEFAULT_FS_ENTRY),
+            buffers,
+            None,
+        )?;
+        Ok(self.register_shader(shader))
+    }
+
+    fn register_shader(&mut self, shader: Shader) -> ShaderHandle {
+        let name = shader.name.clone();
+        self.shaders.push(Some(shader));
+
+        let handle = ShaderHandle(self.shaders.len() - 1);
+        if self.shader_names.insert(name.clone(), handle).is_some() {
+            warn!("A shader named '{name}' was already loaded, it can now only be used by handle.");
+        }
+        handle
+    }
+
+    /// Ends the validation error scope pushed by the caller, returning the error it caught, if any.
+    ///
+    /// The browser can't block on the scope, so on the web the error is logged once it arrives
+    /// and the pipeline is returned as if it were valid.
+    fn pop_validation_scope(&self) -> Result<(), ShaderError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(error) = self.device.pop_error_scope().block_on() {
+            return Err(ShaderError::Compilation(error.to_string()));
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let scope = self.device.pop_error_scope();
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Some(error) = scope.await {
+                    error!("Shader validation failed: {error}");
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Builds the pipelines for WGSL `source` with the given vertex and fragment entry points,
+    /// reading the given vertex `buffers` and binding `object_layout` (if any) at group 1 for the
+    /// [`SceneObject::bind_group`].
+    fn build_shader(
+        &self,
+        name: String,
+        source: &str,
+        (vs_entry, fs_entry): (&str, &str),
+        buffers: &[wgpu::VertexBufferLayout],
+        object_layout: Option<&wgpu::BindGroupLayout>,
+    ) -> Result<Shader, ShaderError> {
+        // Capture validation errors, which would otherwise be fatal, to return them instead.
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: self.label(&name),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+            });
+
+        let pipeline_layout = self.pipeline_layout(
+            &[&self.frame_bind_group_layout]
+                .into_iter()
+                .chain(object_layout)
+                .collect::<Vec<_>>(),
+        );
+
+        let build = |label: &str,
+                     fragment: wgpu::FragmentState,
+                     polygon_mode: wgpu::PolygonMode,
+                     depth_stencil: wgpu::DepthStencilState| {
+            self.device
+                .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: self.label(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &module,
+                        entry_point: vs_entry,
+                        buffers,
+                        compilation_options: Default::default(),
+                    },
+                    fragment: Some(fragment),
+                    primitive: wgpu::PrimitiveState {
+                        front_face: self.front_face,
+                        cull_mode: self.cull_mode,
+                        polygon_mode,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(depth_stencil),
+                    multisample: wgpu::MultisampleState {
+                        count: self.sample_count,
+                        ..Default::default()
+                    },
+                    multiview: None,
+                    cache: None,
+                })
+        };
+        let targets = [Some(self.scene_format.into())];
+        let own_fragment = wgpu::FragmentState {
+            module: &module,
+            entry_point: fs_entry,
+            targets: &targets,
+            compilation_options: Default::default(),
+        };
+        let depth_stencil = wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: Default::default(),
+            bias: Default::default(),
+        };
+
+        let render_pipeline = build(
+            &name,
+            own_fragment.clone(),
+            wgpu::PolygonMode::Fill,
+            depth_stencil.clone(),
+        );
+        let line = self.wireframe_module.as_ref().map(|_| {
+            build(
+                &format!("{name} (lines)"),
+                own_fragment.clone(),
+                wgpu::PolygonMode::Line,
+                depth_stencil.clone(),
+            )
+        });
+        let wireframe = self.wireframe_module.as_ref().map(|wireframe_module| {
+            build(
+                &format!("{name} (wireframe)"),
+                wgpu::FragmentState {
+                    module: wireframe_module,
+                    entry_point: "fs_main",
+                    targets: &targets,
+                    compilation_options: Default::default(),
+                },
+                wgpu::PolygonMode::Line,
+                wgpu::DepthStencilState {
+                    depth_write_enabled: false,
+                    depth_compare: wgpu::CompareFunction::LessEqual,
+                    // Pull the lines towards the camera so they aren't hidden by the solid pass.
+                    bias: wgpu::DepthBiasState {
+                        constant: -2,
+                        slope_scale: -1.0,
+                        clamp: 0.0,
+                    },
+                    ..depth_stencil.clone()
+                },
+            )
+        });
+
+        self.pop_validation_scope()?;
+
+        Ok(Shader {
+            name,
+            path: None,
+            vs_entry: vs_entry.to_owned(),
+            fs_entry: fs_entry.to_owned(),
+            pipeline: render_pipeline,
+            line,
+            wireframe,
+            enabled: true,
+        })
+    }
+
+    /// The built-in shader drawing [`Vertex`] buffers with interpolated vertex colors
+    pub fn vertex_color_shader(&self) -> ShaderHandle {
+        self.vertex_color_shader
+    }
+
+    /// The built-in shader drawing [`TexturedVertex`] buffers sampling the texture bound with
+    /// [`Self::texture_bind_group`]
+    pub fn textured_shader(&self) -> ShaderHandle {
+        self.textured_shader
+    }
+
+    /// The built-in shader drawing [`Vertex`] meshes once per [`Instance`], see
+    /// [`SceneObject::instanced`]
+    pub fn instanced_shader(&self) -> ShaderHandle {
+        self.instanced_shader
+    }
+
+    pub fn shader(&self, handle: ShaderHandle) -> Option<&Shader> {
+        self.shaders.get(handle.0)?.as_ref()
+    }
+
+    /// The most recently loaded shader named `name`
+    pub fn find_shader(&self, name: &str) -> Option<ShaderHandle> {
+        self.shader_names.get(name).copied()
+    }
+
+    pub fn get_pipeline(&self, name: &str) -> Option<&wgpu::RenderPipeline> {
+        Some(&self.shader(self.find_shader(name)?)?.pipeline)
+    }
+
+    pub fn shaders(&self) -> impl Iterator<Item = (ShaderHandle, &Shader)> {
+        self.shaders
+            .iter()
+            .enumerate()
+            .filter_map(|(i, shader)| Some((ShaderHandle(i), shader.as_ref()?)))
+    }
+
+    /// Disabled shaders stay loaded, but objects using them aren't drawn.
+    pub fn set_shader_enabled(&mut self, handle: ShaderHandle, enabled: bool) {
+        if let Some(Some(shader)) = self.shaders.get_mut(handle.0) {
+            shader.enabled = enabled;
+        }
+    }
+
+    /// Unloads the shader along with every object drawn with it.
+    pub fn destroy_shader(&mut self, handle: ShaderHandle) {
+        if let Some(shader) = self.shaders.get_mut(handle.0).and_then(Option::take) {
+            if self.shader_names.get(&shader.name) == Some(&handle) {
+                self.shader_names.remove(&shader.name);
+            }
+        }
+        self.objects.retain(|object| object.pipeline != handle);
+        if self.solo_shader == Some(handle) {
+            self.solo_shader = None;
+        }
+    }
+
+    /// Steps through drawing each shader on its own, then all of them again, returning the
+    /// shader now soloed if any.
+    pub fn cycle_solo_shader(&mut self) -> Option<&Shader> {
+        let next = self
+            .shaders()
+            .map(|(handle, _)| handle)
+            .find(|&handle| self.solo_shader.is_none_or(|solo| handle > solo));
+        self.solo_shader = next;
+        self.shader(next?)
+    }
+
+    /// Switches between drawing geometry as lines and filled, staying filled with a warning if
+    /// the device lacks `POLYGON_MODE_LINE`. Both variants are built with each shader, so this
+    /// takes effect on the next frame.
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        if wireframe && self.wireframe_module.is_none() {
+            warn!("Wireframe mode is unavailable, the device doesn't support POLYGON_MODE_LINE.");
+            return;
+        }
+        self.wireframe = wireframe;
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Toggles drawing geometry again as a wireframe over itself, returning whether it's now on.
+    /// Stays off with a warning if the device lacks `POLYGON_MODE_LINE`.
+    pub fn toggle_wireframe_overlay(&mut self) -> bool {
+        if self.wireframe_module.is_none() {
+            warn!(
+                "Wireframe overlay is unavailable, the device doesn't support POLYGON_MODE_LINE."
+            );
+            return false;
+        }
+
+        self.wireframe_overlay = !self.wireframe_overlay;
+        self.wireframe_overlay
+    }
+
+    pub fn clear_color(&self) -> wgpu::Color {
+        self.clear_color
+    }
+
+    pub fn set_clear_color(&mut self, clear_color: wgpu::Color) {
+        self.clear_color = clear_color;
+    }
+
+    // SCENE
+
+    /// Uploads per-instance data, e.g. [`Instance`]s for the [`Self::instanced_shader`].
+    pub fn create_instance_buffer<T: bytemuck::Pod>(&self, instances: &[T]) -> InstanceBuffer {
+        InstanceBuffer::new(&self.device, instances)
+    }
+
+    /// Replaces the contents of `buffer`, see [`InstanceBuffer::write`].
+    pub fn write_instances<T: bytemuck::Pod>(
+        &self,
+        buffer: &mut InstanceBuffer,
+        instances: &[T],
+    ) -> bool {
+        buffer.write(&self.device, &self.queue, instances)
+    }
+
+    /// Uploads `vertices`, e.g. [`Vertex`]es for drawing with the [`Self::vertex_color_shader`].
+    pub fn create_vertex_buffer<V: bytemuck::Pod>(&self, vertices: &[V]) -> wgpu::Buffer {
+        use wgpu::util::DeviceExt as _;
+
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+    }
+
+    /// Uploads indexed geometry, e.g. to draw with [`SceneObject::mesh`].
+    pub fn upload_mesh<V: bytemuck::Pod>(&self, vertices: &[V], indices: &[u32]) -> Mesh {
+        use wgpu::util::DeviceExt as _;
+
+        Mesh {
+            vertex_buffer: Arc::new(self.create_vertex_buffer(vertices)),
+            index_buffer: Arc::new(self.device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: None,
+                    contents: bytemuck::cast_slice(indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                },
+            )),
+            index_count: indices.len() as u32,
+        }
+    }
+
+    /// The draw calls issued each frame; order doesn't matter as they're sorted before drawing.
+    pub fn objects_mut(&mut self) -> &mut Vec<SceneObject> {
+        &mut self.objects
+    }
+
+    /// Draw calls the objects took in the last frame, not counting the render callback's; each
+    /// object is one, or two with the wireframe overlay.
+    pub fn draw_calls(&self) -> u32 {
+        self.draw_calls
+    }
+
+    /// Fullscreen passes run every frame, in order, after drawing the objects. While there are
+    /// any, the objects are drawn into [`PassInput::Scene`] rather than the frame.
+    pub fn passes_mut(&mut self) -> &mut Vec<Pass> {
+        &mut self.passes
+    }
+
+    /// Creates a `format` target for passes to render into, resized along with the surface.
+    pub fn create_render_target(&mut self, format: wgpu::TextureFormat) -> RenderTargetHandle {
+        self.render_targets.push(RenderTarget::new(
+            &self.device,
+            self.config.width,
+            self.config.height,
+            format,
+        ));
+        RenderTargetHandle(self.render_targets.len() - 1)
+    }
+
+    /// Builds a [`Pass`] pipeline sampling `inputs` textures and writing to `format`, from WGSL
+    /// `source` with `vs_main`/`fs_main` entry points. `vs_main` gets no vertex buffers, so it
+    /// should generate a fullscreen triangle from the vertex index.
+    pub fn create_pass_pipeline(
+        &self,
+        source: &str,
+        inputs: usize,
+        format: wgpu::TextureFormat,
+    ) -> Result<Arc<wgpu::RenderPipeline>, ShaderError> {
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: None,
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+            });
+        let pipeline_layout = self.pipeline_layout(&vec![&self.texture_bind_group_layout; inputs]);
+        let pipeline = self
+            .device
+            .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &module,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                    compilation_options: Default::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &module,
+                    entry_point: "fs_main",
+                    targets: &[Some(format.into())],
+                    compilation_options: Default::default(),
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            });
+
+        self.pop_validation_scope()?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// Enables or disables anti-aliasing the frame with FXAA, after all passes but before the
+    /// text overlay and UI are drawn.
+    pub fn set_fxaa(&mut self, enabled: bool) {
+        if !enabled {
+            self.fxaa_pipeline = None;
+            self.fxaa_target = None;
+            return;
+        }
+        if self.fxaa_pipeline.is_some() {
+            return;
+        }
+
+        match self.create_pass_pipeline(
+            include_str!("../../shaders/fxaa.wgsl"),
+            1,
+            self.config.format,
+        ) {
+            Ok(pipeline) => {
+                self.fxaa_pipeline = Some(pipeline);
+                self.fxaa_target = Some(RenderTarget::new(
+                    &self.device,
+                    self.config.width,
+                    self.config.height,
+                    self.config.format,
+                ));
+            }
+            Err(error) => error!("Built-in FXAA shader failed to compile: {error:?}"),
+        }
+    }
+
+    pub fn fxaa(&self) -> bool {
+        self.fxaa_pipeline.is_some()
+    }
+
+    // HDR
+
+    /// [`HDR_SCENE_FORMAT`] if HDR rendering is requested and possible, otherwise `format`,
+    /// the surface's.
+    fn pick_scene_format(
+        adapter: &wgpu::Adapter,
+        settings: &GraphicsSettings,
+        format: wgpu::TextureFormat,
+    ) -> wgpu::TextureFormat {
+        if !settings.hdr {
+            return format;
+        }
+        if format == HDR_FORMAT {
+            info!("The swapchain is already extended range, drawing into it without tonemapping.");
+            return format;
+        }
+
+        let usages = adapter
+            .get_texture_format_features(HDR_SCENE_FORMAT)
+            .allowed_usages;
+        if !usages
+            .contains(wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING)
+        {
+            warn!("{HDR_SCENE_FORMAT:?} isn't renderable on this adapter, rendering in LDR.");
+            return format;
+        }
+        HDR_SCENE_FORMAT
+    }
+
+    /// Format scene pipelines are built for, which custom pipelines drawing into the main pass
+    /// need to match
+    pub fn scene_format(&self) -> wgpu::TextureFormat {
+        self.scene_format
+    }
+
+    /// Whether the scene is drawn into an HDR target and tonemapped
+    pub fn hdr(&self) -> bool {
+        self.hdr_target.is_some()
+    }
+
+    pub fn tonemap(&self) -> Tonemap {
+        self.tonemap
+    }
+
+    /// Switches the tonemap operator; only has an effect with HDR rendering.
+    pub fn set_tonemap(&mut self, tonemap: Tonemap) {
+        self.tonemap = tonemap;
+        if self.hdr_target.is_none() {
+            return;
+        }
+
+        let operator = match tonemap {
+            Tonemap::Reinhard => 0,
+            Tonemap::Aces => 1,
+        };
+        // Unlike sRGB formats, linear ones don't gamma encode on write.
+        let source = format!(
+            "const OPERATOR: u32 = {operator}u;\nconst ENCODE_SRGB: bool = {};\n{}",
+            !self.config.format.is_srgb(),
+            include_str!("../../shaders/tonemap.wgsl")
+        );
+        match self.create_pass_pipeline(&source, 1, self.config.format) {
+            Ok(pipeline) => self.tonemap_pipeline = Some(pipeline),
+            Err(error) => error!("Built-in tonemap shader failed to compile: {error:?}"),
+        }
+    }
+
+    /// Sprites to draw over the objects next frame
+    pub fn sprites_mut(&mut self) -> &mut SpriteBatch {
+        &mut self.sprite_batch
+    }
+
+    /// Creates a storage buffer holding `data`, bound as `var<storage, read>` if `read_only` and
+    /// `read_write` otherwise.
+    pub fn create_storage_buffer<T: bytemuck::Pod>(
+        &self,
+        data: &[T],
+        read_only: bool,
+    ) -> StorageBuffer {
+        StorageBuffer::new(&self.device, data, read_only)
+    }
+
+    /// Overwrites `buffer` with `data`; see [`StorageBuffer::write`].
+    pub fn update_storage_buffer<T: bytemuck::Pod>(&self, buffer: &mut StorageBuffer, data: &[T]) {
+        buffer.write(&self.queue, data);
+    }
+
+    /// Reads `buffer` back from the GPU, blocking until any work writing it is done.
+    pub fn read_storage_buffer<T: bytemuck::Pod>(
+        &self,
+        buffer: &StorageBuffer,
+    ) -> Result<Vec<T>, ReadbackError> {
+        buffer.read(&self.device, &self.queue)
+    }
+
+    /// Loads a WGSL compute shader from `path` with the compute entry point `entry`.
+    ///
+    /// The bind group layouts are derived from the shader, so bind groups for it are made with
+    /// `pipeline.get_bind_group_layout(group)`.
+    pub fn load_compute_shader(
+        &self,
+        path: impl AsRef<Path>,
+        entry: &str,
+    ) -> Result<Arc<wgpu::ComputePipeline>, ShaderError> {
+        let path = path.as_ref();
+        let source = read_to_string(path)?;
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+
+        let name = path.display().to_string();
+        let module = self
+            .device
+            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: self.label(&name),
+                source: wgpu::ShaderSource::Wgsl(Cow::Owned(source)),
+            });
+        let pipeline = self
+            .device
+            .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: self.label(&name),
+                layout: None,
+                module: &module,
+                entry_point: entry,
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+        self.pop_validation_scope()?;
+        Ok(Arc::new(pipeline))
+    }
+
+    /// Runs `pipeline` once over `x * y * z` workgroups in the next frame, before it's drawn,
+    /// with `bind_groups` bound in order from group 0.
+    pub fn dispatch(
+        &mut self,
+        pipeline: &Arc<wgpu::ComputePipeline>,
+        bind_groups: &[Arc<wgpu::BindGroup>],
+        x: u32,
+        y: u32,
+        z: u32,
+    ) {
+        self.pending_dispatches.push(ComputeDispatch {
+            pipeline: pipeline.clone(),
+            bind_groups: bind_groups.to_vec(),
+            workgroups: [x, y, z],
+        });
+    }
+
+    /// Compute work dispatched every frame, in order, before any drawing.
+    pub fn compute_dispatches_mut(&mut self) -> &mut Vec<ComputeDispatch> {
+        &mut self.compute_dispatches
+    }
+
+    /// Shows `text` in the top left corner over everything else, or hides it if `None`.
+    pub fn set_overlay_text(&mut self, text: Option<&str>) {
+        self.text_overlay_visible = text.is_some();
+        if let Some(text) = text {
+            self.text_overlay
+                .get_or_insert_with(|| {
+                    TextOverlay::new(&self.device, &self.queue, self.config.format)
+                })
+                .set_text(text);
+        }
+    }
+
+    // DRAWING
+
+    /// Draws every object into a single acquired swapchain frame, in one pass, and presents it.
+    pub fn draw(&mut self) -> Result<FrameTimings, wgpu::SurfaceError> {
+        self.draw_with_overlay(|_, _, _, _| {})
+    }
+
+    /// Draws a frame, letting `overlay` record extra passes onto the final, resolved frame view.
+    /// Fails if no swapchain texture could be acquired.
+    pub fn draw_with_overlay(
+        &mut self,
+        overlay: impl FnMut(&wgpu::Device, &wgpu::Queue, &mut wgpu::CommandEncoder, &wgpu::TextureView),
+    ) -> Result<FrameTimings, wgpu::SurfaceError> {
+        self.draw_with(|_| {}, overlay)
+    }
+
+    /// Draws a frame, letting `scene` record its own draws into the main pass after the
+    /// built-in objects and `overlay` record extra passes onto the final, resolved frame view.
+    pub fn draw_with(
+        &mut self,
+        mut scene: impl FnMut(&mut DrawContext),
+        mut overlay: impl FnMut(
+            &wgpu::Device,
+            &wgpu::Queue,
+            &mut wgpu::CommandEncoder,
+            &wgpu::TextureView,
+        ),
+    ) -> Result<FrameTimings, wgpu::SurfaceError> {
+        let start = Instant::now();
+
+        self.reload_changed_shaders();
+
+        // Sort once up front; in place, so later frames are already (nearly) sorted.
+        if cfg!(debug_assertions) {
+            let unsorted = scene::pipeline_switches(&self.objects);
+            scene::sort_draws(&mut self.objects);
+            let sorted = scene::pipeline_switches(&self.objects);
+            if sorted < unsorted {
+                debug!("Sorting draws reduced pipeline switches from {unsorted} to {sorted}.");
+            }
+        } else {
+            scene::sort_draws(&mut self.objects);
+        }
+
+        // Waiting on the GPU counts towards the acquire time, like waiting in
+        // `get_current_texture` does.
+        let acquire_start = Instant::now();
+        if let Some(max_frames_in_flight) = self.max_frames_in_flight {
+            while self.frames_in_flight.len() >= max_frames_in_flight as usize {
+                let oldest = self.frames_in_flight.pop_front().unwrap();
+                self.device
+                    .poll(wgpu::Maintain::WaitForSubmissionIndex(oldest));
+            }
+        }
+        let (frame, view) = match &self.target {
+            FrameTarget::Surface(surface) => {
+                let frame = surface.get_current_texture()?;
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+                (Some(frame), view)
+            }
+            FrameTarget::Offscreen(texture) => (
+                None,
+                texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            ),
+        };
+        let acquire = acquire_start.elapsed();
+
+        let uniforms = FrameUniforms::new(
+            self.view_projection,
+            [self.config.width as f32, self.config.height as f32],
+            self.start_time.elapsed().as_secs_f32(),
+        );
+        self.queue
+            .write_buffer(&self.frame_uniforms, 0, bytemuck::bytes_of(&uniforms));
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: self.label("frame encoder"),
+            });
+
+        // Every-frame work first, then this frame's one-off dispatches.
+        let pending = std::mem::take(&mut self.pending_dispatches);
+        let dispatches = self.compute_dispatches.iter().chain(&pending);
+        if !self.compute_dispatches.is_empty() || !pending.is_empty() {
+            if self.async_compute {
+                // wgpu only exposes a single queue, so this can't truly run concurrently, but
+                // submitting compute on its own gives the driver the chance to overlap it.
+                let mut compute_encoder =
+                    self.device
+                        .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: self.label("compute encoder"),
+                        });
+                compute::record(&mut compute_encoder, dispatches);
+                self.queue.submit(Some(compute_encoder.finish()));
+            } else {
+                compute::record(&mut encoder, dispatches);
+            }
+        }
+
+        let capturing = self.prepare_capture();
+        if !self.passes.is_empty() && self.scene_target.is_none() {
+            self.scene_target = Some(RenderTarget::new(
+                &self.device,
+                self.config.width,
+                self.config.height,
+                self.config.format,
+            ));
+        }
+        // When capturing, everything up to the overlays is composited offscreen so it can be
+        // read back, then copied to the frame.
+        let composite_view = match &self.capture_target {
+            Some(capture_target) if capturing => capture_target.view(),
+            _ => &view,
+        };
+        // With FXAA, everything up to the overlays is drawn offscreen and anti-aliased into
+        // the (composite) frame afterwards.
+        let post_view = match &self.fxaa_target {
+            Some(fxaa_target) => fxaa_target.view(),
+            None => composite_view,
+        };
+        let ldr_scene_view = match &self.scene_target {
+            Some(scene_target) if !self.passes.is_empty() => scene_target.view(),
+            _ => post_view,
+        };
+        // With HDR, the scene is tonemapped into where it would have been drawn otherwise.
+        let scene_view = match &self.hdr_target {
+            Some(hdr_target) => hdr_target.view(),
+            None => ldr_scene_view,
+        };
+
+        self.draw_calls = self.record_scene(
+            &mut encoder,
+            scene_view,
+            self.gpu_timer.as_ref().map(GpuTimer::timestamp_writes),
+            &mut scene,
+        );
+        self.sprite_batch.render(
+            &self.device,
+            &self.queue,
+            &mut encoder,
+            scene_view,
+            &self.frame_bind_group,
+        );
+        self.record_tonemap(&mut encoder, ldr_scene_view);
+        self.record_passes(&mut encoder, post_view);
+        if let (Some(pipeline), Some(fxaa_target)) = (&self.fxaa_pipeline, &self.fxaa_target) {
+            let bind_group = self.pass_bind_group(fxaa_target);
+            self.record_fullscreen_pass(&mut encoder, pipeline, &[bind_group], composite_view);
+        }
+        if let (true, Some(pipeline), Some(capture_target)) =
+            (capturing, &self.capture_pipeline, &self.capture_target)
+        {
+            let bind_group = self.pass_bind_group(capture_target);
+            self.record_fullscreen_pass(&mut encoder, pipeline, &[bind_group], &view);
+        }
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.resolve(&mut encoder);
+        }
+        if let (Some(text_overlay), true) = (&mut self.text_overlay, self.text_overlay_visible) {
+            text_overlay.render(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                &view,
+                self.config.width,
+                self.config.height,
+            );
+        }
+        overlay(&self.device, &self.queue, &mut encoder, &view);
+
+        let submission = self.queue.submit(Some(encoder.finish()));
+        if self.max_frames_in_flight.is_some() {
+            self.frames_in_flight.push_back(submission);
+        }
+        if let Some(frame) = frame {
+            frame.present();
+        }
+        if capturing {
+            self.capture = Some(self.read_capture());
+        }
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.poll(&self.device);
+        }
+
+        Ok(FrameTimings {
+            acquire,
+            cpu: start.elapsed().saturating_sub(acquire),
+        })
+    }
+
+    /// Records each of the [`Self::passes`] in order, those without an output drawing into `view`.
+    fn record_passes(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        for pass in &self.passes {
+            let bind_groups = pass.inputs.iter().map(|input| {
+                let input = match input {
+                    PassInput::Scene => self.scene_target.as_ref(),
+                    PassInput::Target(handle) => self.render_targets.get(handle.0),
+                }?;
+                Some(self.pass_bind_group(input))
+            });
+            // Skip passes referring to targets that don't exist, rather than binding the wrong ones.
+            let Some(bind_groups) = bind_groups.collect::<Option<Vec<_>>>() else {
+                continue;
+            };
+            let output = match pass.output {
+                Some(handle) => match self.render_targets.get(handle.0) {
+                    Some(target) => target.view(),
+                    None => continue,
+                },
+                None => view,
+            };
+
+            self.record_fullscreen_pass(encoder, &pass.pipeline, &bind_groups, output);
+        }
+    }
+
+    /// Tonemaps the HDR target into `view`, if rendering in HDR.
+    fn record_tonemap(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if let (Some(pipeline), Some(hdr_target)) = (&self.tonemap_pipeline, &self.hdr_target) {
+            let bind_group = self.pass_bind_group(hdr_target);
+            self.record_fullscreen_pass(encoder, pipeline, &[bind_group], view);
+        }
+    }
+
+    /// Binds `input` for sampling by a pass, as a texture at binding 0 and a sampler at 1.
+    fn pass_bind_group(&self, input: &RenderTarget) -> wgpu::BindGroup {
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.pass_sampler),
+                },
+            ],
+        })
+    }
+
+    /// Draws a fullscreen triangle with `pipeline` into `output`, with `bind_groups` bound at
+    /// groups `0..bind_groups.len()`.
+    fn record_fullscreen_pass(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        pipeline: &wgpu::RenderPipeline,
+        bind_groups: &[wgpu::BindGroup],
+        output: &wgpu::TextureView,
+    ) {
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: self.label("post pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        rp.set_pipeline(pipeline);
+        for (index, bind_group) in bind_groups.iter().enumerate() {
+            rp.set_bind_group(index as u32, bind_group, &[]);
+        }
+        rp.draw(0..3, 0..1);
+    }
+
+    /// Records the render pass drawing every object into `view`, returning the draw calls the
+    /// objects took.
+    fn record_scene(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'_>>,
+        scene: &mut dyn FnMut(&mut DrawContext),
+    ) -> u32 {
+        // Render into the multisampled target and resolve into the frame, if enabled.
+        let (target, resolve_target) = match &self.msaa_view {
+            Some(msaa_view) => (msaa_view, Some(view)),
+            None => (view, None),
+        };
+
+        let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: self.label("main pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    // Kept around for `read_depth`.
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes,
+            occlusion_query_set: None,
+        });
+        rp.set_bind_group(0, &self.frame_bind_group, &[]);
+
+        let mut draw_calls = self.draw_objects(&mut rp, |shader| {
+            if self.wireframe {
+                shader.line.as_ref()
+            } else {
+                Some(&shader.pipeline)
+            }
+        });
+
+        if self.wireframe_overlay {
+            draw_calls += self.draw_objects(&mut rp, |shader| shader.wireframe.as_ref());
+        }
+
+        scene(&mut DrawContext::new(self, &mut rp));
+        draw_calls
+    }
+
+    /// Draws every (non-soloed-out) object with the pipeline `select` picks from its shader,
+    /// skipping objects for which it picks none, and returns the number of draw calls.
+    fn draw_objects<'p>(
+        &'p self,
+        rp: &mut wgpu::RenderPass<'_>,
+        select: impl Fn(&'p Shader) -> Option<&'p wgpu::RenderPipeline>,
+    ) -> u32 {
+        let mut draw_calls = 0;
+        // Only touch pipeline and bind group state when it actually changes.
+        let mut current_pipeline = None;
+        let mut current_bind_group = None;
+        for object in &self.objects {
+            if self.solo_shader.is_some_and(|solo| solo != object.pipeline) {
+                continue;
+            }
+            let Some(pipeline) = self
+                .shader(object.pipeline)
+                .filter(|shader| shader.enabled)
+                .and_then(&select)
+            else {
+                continue;
+            };
+
+            if current_pipeline.replace(object.pipeline) != Some(object.pipeline) {
+                rp.set_pipeline(pipeline);
+            }
+            if let Some(bind_group) = &object.bind_group {
+                let id = bind_group.global_id();
+                if current_bind_group.replace(id) != Some(id) {
+                    rp.set_bind_group(1, bind_group, &[]);
+                }
+            }
+            if let Some(vertex_buffer) = &object.vertex_buffer {
+                rp.set_vertex_buffer(0, vertex_buffer.slice(..));
+            }
+            if let Some(instance_buffer) = &object.instance_buffer {
+                rp.set_vertex_buffer(1, instance_buffer.slice(..));
+            }
+            match &object.index_buffer {
+                Some(index_buffer) => {
+                    rp.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    rp.draw_indexed(object.vertices.clone(), 0, object.instances.clone());
+                }
+                None => rp.draw(object.vertices.clone(), object.instances.clone()),
+            }
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+}
+
+/// Converts a `[0, 1]` depth buffer value from a standard perspective projection into the
+/// view-space distance between `near` and `far`.
+pub fn linearize_depth(depth: f32, near: f32, far: f32) -> f32 {
+    near * far / (far - depth * (far - near))
+}
@@ -0,0 +1,123 @@
+use glyphon::{
+    Attrs, Buffer, Cache, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache,
+    TextArea, TextAtlas, TextBounds, TextRenderer, Viewport,
+};
+use log::*;
+
+/// Font size of the overlay text in pixels
+const FONT_SIZE: f32 = 16.0;
+/// Distance of the text from the top left corner in pixels
+const MARGIN: f32 = 8.0;
+
+/// A block of text drawn in the top left corner over everything else, e.g. frame stats.
+pub struct TextOverlay {
+    font_system: FontSystem,
+    swash_cache: SwashCache,
+    viewport: Viewport,
+    atlas: TextAtlas,
+    renderer: TextRenderer,
+    buffer: Buffer,
+}
+
+impl std::fmt::Debug for TextOverlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextOverlay").finish_non_exhaustive()
+    }
+}
+
+impl TextOverlay {
+    /// Loads the system fonts, so this takes a moment.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, format: wgpu::TextureFormat) -> Self {
+        let mut font_system = FontSystem::new();
+        let cache = Cache::new(device);
+        let mut atlas = TextAtlas::new(device, queue, &cache, format);
+        // Drawn onto the resolved frame, so never multisampled.
+        let renderer = TextRenderer::new(&mut atlas, device, Default::default(), None);
+        let buffer = Buffer::new(&mut font_system, Metrics::new(FONT_SIZE, FONT_SIZE * 1.25));
+
+        Self {
+            font_system,
+            swash_cache: SwashCache::new(),
+            viewport: Viewport::new(device, &cache),
+            atlas,
+            renderer,
+            buffer,
+        }
+    }
+
+    pub fn set_text(&mut self, text: &str) {
+        self.buffer.set_text(
+            &mut self.font_system,
+            text,
+            Attrs::new().family(Family::Monospace),
+            Shaping::Basic,
+        );
+    }
+
+    /// Draws the text over `view`, which is `width` by `height` pixels.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        width: u32,
+        height: u32,
+    ) {
+        self.viewport.update(queue, Resolution { width, height });
+        self.buffer.set_size(
+            &mut self.font_system,
+            Some(width as f32 - MARGIN),
+            Some(height as f32 - MARGIN),
+        );
+        self.buffer.shape_until_scroll(&mut self.font_system, false);
+
+        if let Err(error) = self.renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            &self.viewport,
+            [TextArea {
+                buffer: &self.buffer,
+                left: MARGIN,
+                top: MARGIN,
+                scale: 1.0,
+                bounds: TextBounds {
+                    left: 0,
+                    top: 0,
+                    right: width as i32,
+                    bottom: height as i32,
+                },
+                default_color: Color::rgb(255, 255, 255),
+                custom_glyphs: &[],
+            }],
+            &mut self.swash_cache,
+        ) {
+            warn!("Failed to prepare overlay text: {error}");
+            return;
+        }
+
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            if let Err(error) = self.renderer.render(&self.atlas, &self.viewport, &mut rp) {
+                warn!("Failed to render overlay text: {error}");
+            }
+        }
+
+        self.atlas.trim();
+    }
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+/// An offscreen color texture, sized to the surface, that passes render into and sample from.
+#[derive(Debug)]
+pub struct RenderTarget {
+    format: wgpu::TextureFormat,
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl RenderTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // Copyable so captures can read it back.
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        Self {
+            format,
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            texture,
+        }
+    }
+
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.texture
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+}
+
+/// Index of a [`RenderTarget`] owned by a `GraphicsContext`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetHandle(pub(crate) usize);
+
+/// A texture a [`Pass`] samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassInput {
+    /// The scene objects were drawn into, before any pass ran
+    Scene,
+    Target(RenderTargetHandle),
+}
+
+/// A fullscreen draw run after the scene, e.g. post-processing. Passes run in order, so each can
+/// sample the outputs of those before it.
+#[derive(Debug, Clone)]
+pub struct Pass {
+    /// Draws 3 vertices without buffers, e.g. built with
+    /// `GraphicsContext::create_pass_pipeline`
+    pub pipeline: Arc<wgpu::RenderPipeline>,
+    /// Bound at groups `0..inputs.len()` in order, each as a texture at binding 0 and a filtering
+    /// sampler at binding 1
+    pub inputs: Vec<PassInput>,
+    /// Target to render into, or `None` for the frame itself; the last pass should draw to the
+    /// frame, as nothing else will
+    pub output: Option<RenderTargetHandle>,
+}
@@ -0,0 +1,106 @@
+use crate::util::error::ReadbackError;
+
+/// Copies a single-sampled 2D `texture` back to the CPU, returning its tightly packed texels.
+///
+/// Rows are padded to `COPY_BYTES_PER_ROW_ALIGNMENT` (256 bytes) for the copy and unpadded here,
+/// so the result is always `width * height * bytes_per_texel` long. The texture needs `COPY_SRC`.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    aspect: wgpu::TextureAspect,
+    bytes_per_texel: u32,
+) -> Result<Vec<u8>, ReadbackError> {
+    if texture.sample_count() > 1 {
+        return Err(ReadbackError::Multisampled);
+    }
+
+    let (width, height) = (texture.width(), texture.height());
+    let unpadded_bytes_per_row = width * bytes_per_texel;
+    let padded_bytes_per_row =
+        unpadded_bytes_per_row.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = buffer.slice(..);
+    map_blocking(device, slice)?;
+
+    let texels = slice
+        .get_mapped_range()
+        .chunks_exact(padded_bytes_per_row as usize)
+        .flat_map(|row| &row[..unpadded_bytes_per_row as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+
+    Ok(texels)
+}
+
+/// Copies the first `size` bytes of `buffer` back to the CPU. The buffer needs `COPY_SRC`, and
+/// `size` has to be a multiple of `COPY_BUFFER_ALIGNMENT` (4 bytes).
+pub fn read_buffer(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    size: u64,
+) -> Result<Vec<u8>, ReadbackError> {
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: None,
+        size,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    map_blocking(device, slice)?;
+    let bytes = slice.get_mapped_range().to_vec();
+    staging.unmap();
+
+    Ok(bytes)
+}
+
+/// Maps `slice` for reading, blocking until the GPU is done with it.
+fn map_blocking(device: &wgpu::Device, slice: wgpu::BufferSlice) -> Result<(), ReadbackError> {
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .map_err(|_| ReadbackError::Disconnected)?
+        .map_err(ReadbackError::Map)
+}
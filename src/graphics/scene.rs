@@ -0,0 +1,95 @@
+use std::{ops::Range, sync::Arc};
+
+use super::{instance::InstanceBuffer, mesh::Mesh, ShaderHandle};
+
+/// A single draw call and the state it needs bound.
+#[derive(Debug, Clone)]
+pub struct SceneObject {
+    pub pipeline: ShaderHandle,
+    /// Bound at group 1, after the [`FrameUniforms`](super::uniforms::FrameUniforms), if any
+    pub bind_group: Option<Arc<wgpu::BindGroup>>,
+    /// Bound at slot 0, if any
+    pub vertex_buffer: Option<Arc<wgpu::Buffer>>,
+    /// Bound at slot 1 for per-instance data, if any
+    pub instance_buffer: Option<Arc<wgpu::Buffer>>,
+    /// `u32` indices into `vertex_buffer`, if drawing indexed
+    pub index_buffer: Option<Arc<wgpu::Buffer>>,
+    /// Range of indices instead if `index_buffer` is set
+    pub vertices: Range<u32>,
+    pub instances: Range<u32>,
+}
+
+impl SceneObject {
+    /// Draws the vertex-buffer-less fullscreen triangle with `pipeline`.
+    pub fn fullscreen_triangle(pipeline: ShaderHandle) -> Self {
+        Self {
+            pipeline,
+            bind_group: None,
+            vertex_buffer: None,
+            instance_buffer: None,
+            index_buffer: None,
+            vertices: 0..3,
+            instances: 0..1,
+        }
+    }
+
+    /// Draws the first `count` vertices of `vertex_buffer` with `pipeline`.
+    pub fn vertices(pipeline: ShaderHandle, vertex_buffer: Arc<wgpu::Buffer>, count: u32) -> Self {
+        Self {
+            pipeline,
+            bind_group: None,
+            vertex_buffer: Some(vertex_buffer),
+            instance_buffer: None,
+            index_buffer: None,
+            vertices: 0..count,
+            instances: 0..1,
+        }
+    }
+
+    /// Draws all of `mesh` with `pipeline`.
+    pub fn mesh(pipeline: ShaderHandle, mesh: &Mesh) -> Self {
+        Self {
+            pipeline,
+            bind_group: None,
+            vertex_buffer: Some(mesh.vertex_buffer.clone()),
+            instance_buffer: None,
+            index_buffer: Some(mesh.index_buffer.clone()),
+            vertices: 0..mesh.index_count,
+            instances: 0..1,
+        }
+    }
+
+    /// Draws every instance in `instances` of `mesh` in a single draw call with `pipeline`,
+    /// e.g. the [`GraphicsContext::instanced_shader`](super::GraphicsContext::instanced_shader).
+    pub fn instanced(pipeline: ShaderHandle, mesh: &Mesh, instances: &InstanceBuffer) -> Self {
+        Self {
+            instance_buffer: Some(instances.buffer().clone()),
+            instances: 0..instances.count(),
+            ..Self::mesh(pipeline, mesh)
+        }
+    }
+
+    fn sort_key(&self) -> (ShaderHandle, Option<wgpu::Id<wgpu::BindGroup>>) {
+        (
+            self.pipeline,
+            self.bind_group
+                .as_ref()
+                .map(|bind_group| bind_group.global_id()),
+        )
+    }
+}
+
+/// Orders `objects` by pipeline then bind group so each is set as few times as possible.
+pub fn sort_draws(objects: &mut [SceneObject]) {
+    // Stable, so objects sharing state keep their submission order.
+    objects.sort_by_key(SceneObject::sort_key);
+}
+
+/// Number of `set_pipeline` calls needed to draw `objects` in order.
+pub fn pipeline_switches(objects: &[SceneObject]) -> usize {
+    let mut current = None;
+    objects
+        .iter()
+        .filter(|object| current.replace(object.pipeline) != Some(object.pipeline))
+        .count()
+}
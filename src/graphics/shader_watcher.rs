@@ -0,0 +1,49 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use log::*;
+use notify::{RecursiveMode, Watcher as _};
+
+/// Watches a directory for modified `.wgsl` files.
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    // Stops watching once dropped.
+    _watcher: notify::RecommendedWatcher,
+    changes: Receiver<PathBuf>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: impl AsRef<Path>) -> notify::Result<Self> {
+        let (sender, changes) = channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| match event {
+                Ok(event) if event.kind.is_modify() || event.kind.is_create() => {
+                    for path in event.paths {
+                        if path.extension().is_some_and(|ext| ext == "wgsl") {
+                            let _ = sender.send(path);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(error) => warn!("Shader watcher error: {error}"),
+            })?;
+        watcher.watch(dir.as_ref(), RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Returns the canonical paths of every shader modified since the last call, without
+    /// duplicates as editors often write a file several times per save.
+    pub fn changed(&self) -> HashSet<PathBuf> {
+        self.changes
+            .try_iter()
+            .filter_map(|path| path.canonicalize().ok())
+            .collect()
+    }
+}
@@ -0,0 +1,200 @@
+use std::borrow::Cow;
+use std::mem::{offset_of, size_of};
+use std::sync::Arc;
+
+use glam::Vec2;
+
+use super::instance::InstanceBuffer;
+
+/// A single quad, matching `InstanceInput` in `shaders/sprite.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct SpriteInstance {
+    /// World position of the bottom left corner
+    position: [f32; 2],
+    size: [f32; 2],
+    /// Linear RGBA, multiplied with the texture
+    color: [f32; 4],
+}
+
+impl SpriteInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: offset_of!(SpriteInstance, position) as u64,
+            shader_location: 0,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x2,
+            offset: offset_of!(SpriteInstance, size) as u64,
+            shader_location: 1,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x4,
+            offset: offset_of!(SpriteInstance, color) as u64,
+            shader_location: 2,
+        },
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: size_of::<SpriteInstance>() as u64,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Collects textured quads over a frame and draws them on top of the scene with one instanced
+/// draw per texture.
+///
+/// Sprites are grouped by texture, so sprites with the same texture are drawn in the order they
+/// were added but the order between textures isn't kept.
+#[derive(Debug)]
+pub struct SpriteBatch {
+    pipeline: wgpu::RenderPipeline,
+    /// Sprites to draw next frame and the texture bind group of each
+    sprites: Vec<(Arc<wgpu::BindGroup>, SpriteInstance)>,
+    /// Reused and grown across frames
+    instances: Option<InstanceBuffer>,
+}
+
+impl SpriteBatch {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        frame_bind_group_layout: &wgpu::BindGroupLayout,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: None,
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!(
+                "../../shaders/sprite.wgsl"
+            ))),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[frame_bind_group_layout, texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_main",
+                buffers: &[SpriteInstance::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            sprites: vec![],
+            instances: None,
+        }
+    }
+
+    /// Queues `texture`, bound with `GraphicsContext::texture_bind_group`, to be drawn next frame
+    /// as a `size` quad with its bottom left corner at `position`, tinted by linear RGBA `color`.
+    pub fn draw(
+        &mut self,
+        texture: &Arc<wgpu::BindGroup>,
+        position: Vec2,
+        size: Vec2,
+        color: [f32; 4],
+    ) {
+        self.sprites.push((
+            texture.clone(),
+            SpriteInstance {
+                position: position.to_array(),
+                size: size.to_array(),
+                color,
+            },
+        ));
+    }
+
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Draws the queued sprites over `view` and clears them for the next frame.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        frame_bind_group: &wgpu::BindGroup,
+    ) {
+        if self.sprites.is_empty() {
+            return;
+        }
+
+        // Stable, so sprites sharing a texture keep their order.
+        self.sprites.sort_by_key(|(texture, _)| texture.global_id());
+        let data: Vec<_> = self.sprites.iter().map(|&(_, sprite)| sprite).collect();
+        let instances = match &mut self.instances {
+            Some(instances) => {
+                instances.write(device, queue, &data);
+                instances
+            }
+            None => self.instances.insert(InstanceBuffer::new(device, &data)),
+        };
+
+        {
+            let mut rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            rp.set_pipeline(&self.pipeline);
+            rp.set_bind_group(0, frame_bind_group, &[]);
+            rp.set_vertex_buffer(0, instances.buffer().slice(..));
+
+            // One draw per run of sprites sharing a texture.
+            let mut start = 0;
+            for run in self
+                .sprites
+                .chunk_by(|(a, _), (b, _)| a.global_id() == b.global_id())
+            {
+                let end = start + run.len() as u32;
+                rp.set_bind_group(1, &run[0].0, &[]);
+                rp.draw(0..6, start..end);
+                start = end;
+            }
+        }
+
+        self.sprites.clear();
+    }
+}
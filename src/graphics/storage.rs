@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use log::*;
+
+use super::readback;
+use crate::util::error::ReadbackError;
+
+/// A storage buffer for compute shaders or data-driven rendering, which can be rewritten and
+/// read back.
+#[derive(Debug, Clone)]
+pub struct StorageBuffer {
+    buffer: Arc<wgpu::Buffer>,
+    /// Bytes of data, without the padding up to `COPY_BUFFER_ALIGNMENT`
+    len: u64,
+    /// Whether shaders bind it as `var<storage, read>` rather than `read_write`
+    read_only: bool,
+}
+
+impl StorageBuffer {
+    pub fn new<T: bytemuck::Pod>(device: &wgpu::Device, data: &[T], read_only: bool) -> Self {
+        let contents: &[u8] = bytemuck::cast_slice(data);
+        // Copies go in 4 byte steps, and empty buffers can't be bound.
+        let size = (contents.len() as u64)
+            .max(wgpu::COPY_BUFFER_ALIGNMENT)
+            .next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_DST
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: true,
+        });
+        buffer.slice(..).get_mapped_range_mut()[..contents.len()].copy_from_slice(contents);
+        buffer.unmap();
+
+        Self {
+            buffer: Arc::new(buffer),
+            len: contents.len() as u64,
+            read_only,
+        }
+    }
+
+    /// Overwrites the buffer from the start with `data`. The size is fixed so existing bind
+    /// groups stay valid; data that doesn't fit is rejected with a warning.
+    pub fn write<T: bytemuck::Pod>(&mut self, queue: &wgpu::Queue, data: &[T]) {
+        let contents: &[u8] = bytemuck::cast_slice(data);
+        if contents.len() as u64 > self.buffer.size() {
+            warn!(
+                "Tried to write {} bytes into a {} byte storage buffer, ignoring it.",
+                contents.len(),
+                self.buffer.size()
+            );
+            return;
+        }
+
+        queue.write_buffer(&self.buffer, 0, contents);
+        self.len = contents.len() as u64;
+    }
+
+    /// Copies the data last written back from the GPU, including any changes shaders made to it.
+    /// Blocks until the GPU is done.
+    pub fn read<T: bytemuck::Pod>(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Result<Vec<T>, ReadbackError> {
+        let bytes = readback::read_buffer(device, queue, &self.buffer, self.buffer.size())?;
+        Ok(bytemuck::pod_collect_to_vec(&bytes[..self.len as usize]))
+    }
+
+    /// Layout entry binding this buffer at `binding` for `visibility`; vertex shaders can only
+    /// see read-only storage buffers.
+    pub fn layout_entry(
+        &self,
+        binding: u32,
+        visibility: wgpu::ShaderStages,
+    ) -> wgpu::BindGroupLayoutEntry {
+        wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage {
+                    read_only: self.read_only,
+                },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }
+    }
+
+    /// The entry binding this buffer at `binding` in a bind group
+    pub fn bind_group_entry(&self, binding: u32) -> wgpu::BindGroupEntry<'_> {
+        wgpu::BindGroupEntry {
+            binding,
+            resource: self.buffer.as_entire_binding(),
+        }
+    }
+
+    pub fn buffer(&self) -> &Arc<wgpu::Buffer> {
+        &self.buffer
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+}
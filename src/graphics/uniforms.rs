@@ -0,0 +1,49 @@
+/// Per-frame data bound at group 0, binding 0 of every shader, matching
+///
+/// ```wgsl
+/// struct Frame {
+///     view_proj: mat4x4<f32>,
+///     resolution: vec2<f32>,
+///     time: f32,
+/// }
+/// @group(0) @binding(0) var<uniform> frame: Frame;
+/// ```
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct FrameUniforms {
+    /// World to clip space transform of the active camera
+    pub view_proj: [[f32; 4]; 4],
+    /// Size of the render target in pixels
+    pub resolution: [f32; 2],
+    /// Seconds since the graphics context was created
+    pub time: f32,
+    // Uniform structs are padded to 16 bytes.
+    _pad: f32,
+}
+
+impl FrameUniforms {
+    pub fn new(view_proj: glam::Mat4, resolution: [f32; 2], time: f32) -> Self {
+        Self {
+            view_proj: view_proj.to_cols_array_2d(),
+            resolution,
+            time,
+            _pad: 0.0,
+        }
+    }
+
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+    }
+}
@@ -0,0 +1,450 @@
+#[cfg(feature = "gamepad")]
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, Ime, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, KeyCode, ModifiersState, NamedKey, PhysicalKey};
+
+/// Approximate pixels per scroll line for touchpads reporting pixel deltas
+const PIXELS_PER_LINE: f32 = 20.0;
+/// Default fraction of an analog axis' range around the center that reads as zero
+#[cfg(feature = "gamepad")]
+pub const DEFAULT_DEADZONE: f32 = 0.15;
+
+/// Something typed, in the order it was typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextEvent {
+    /// Printable text, possibly several characters at once from an IME
+    Text(String),
+    Backspace,
+    Delete,
+    Enter,
+}
+
+/// Keyboard and mouse state accumulated from window events, queried by game code each frame.
+#[derive(Debug)]
+pub struct InputState {
+    /// `None` while the cursor is outside the window
+    cursor_position: Option<PhysicalPosition<f64>>,
+    /// Cursor movement since the last frame in physical pixels
+    cursor_delta: (f64, f64),
+    /// Raw mouse movement since the last frame, recorded while the window is focused
+    mouse_motion: (f64, f64),
+    held_buttons: HashSet<MouseButton>,
+    /// Scrolled lines since the last frame, positive away from the user
+    scroll_delta: f32,
+    held_keys: HashSet<KeyCode>,
+    /// Keys that went down since the last frame, ignoring key repeat
+    pressed_keys: HashSet<KeyCode>,
+    /// Keys that went up since the last frame
+    released_keys: HashSet<KeyCode>,
+    modifiers: ModifiersState,
+    /// Printable text typed since the last frame, with the keyboard layout and IME applied
+    text_input: String,
+    /// Typed text and editing keys since the last frame
+    text_events: Vec<TextEvent>,
+    /// Whether an IME is active, in which case text only arrives once committed
+    ime_enabled: bool,
+    /// Connected gamepads
+    #[cfg(feature = "gamepad")]
+    gamepads: HashMap<gilrs::GamepadId, GamepadState>,
+    /// Applied to the axes of every gamepad
+    #[cfg(feature = "gamepad")]
+    gamepad_deadzone: f32,
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        Self {
+            cursor_position: None,
+            cursor_delta: (0.0, 0.0),
+            mouse_motion: (0.0, 0.0),
+            held_buttons: HashSet::new(),
+            scroll_delta: 0.0,
+            held_keys: HashSet::new(),
+            pressed_keys: HashSet::new(),
+            released_keys: HashSet::new(),
+            modifiers: ModifiersState::empty(),
+            text_input: String::new(),
+            text_events: vec![],
+            ime_enabled: false,
+            #[cfg(feature = "gamepad")]
+            gamepads: HashMap::new(),
+            #[cfg(feature = "gamepad")]
+            gamepad_deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl InputState {
+    pub fn cursor_position(&self) -> Option<PhysicalPosition<f64>> {
+        self.cursor_position
+    }
+
+    pub fn cursor_delta(&self) -> (f64, f64) {
+        self.cursor_delta
+    }
+
+    /// Mouse movement since the last frame straight from the device, in unspecified units and
+    /// without acceleration; unlike [`Self::cursor_delta`] it keeps going at the screen edge and
+    /// while the cursor is locked, so it's what camera look should use. Only recorded while the
+    /// window is focused.
+    pub fn mouse_motion(&self) -> (f64, f64) {
+        self.mouse_motion
+    }
+
+    pub fn is_button_held(&self, button: MouseButton) -> bool {
+        self.held_buttons.contains(&button)
+    }
+
+    pub fn scroll_delta(&self) -> f32 {
+        self.scroll_delta
+    }
+
+    pub fn is_held(&self, key: KeyCode) -> bool {
+        self.held_keys.contains(&key)
+    }
+
+    /// Whether `key` went down this frame; unlike key events, not repeated while held
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        self.released_keys.contains(&key)
+    }
+
+    /// Currently held modifier keys
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Overrides the held modifiers, e.g. to exercise shortcuts without a window.
+    pub fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    pub fn ctrl(&self) -> bool {
+        self.modifiers.control_key()
+    }
+
+    pub fn shift(&self) -> bool {
+        self.modifiers.shift_key()
+    }
+
+    pub fn alt(&self) -> bool {
+        self.modifiers.alt_key()
+    }
+
+    /// Windows key on Windows, Command on macOS
+    pub fn logo(&self) -> bool {
+        self.modifiers.super_key()
+    }
+
+    // TEXT
+
+    /// Printable text typed this frame, e.g. for a text field. Backspace and Enter aren't
+    /// included, see [`InputState::text_events`].
+    pub fn text_input(&self) -> &str {
+        &self.text_input
+    }
+
+    /// Text typed this frame along with the editing keys pressed in between, repeating while
+    /// held
+    pub fn text_events(&self) -> &[TextEvent] {
+        &self.text_events
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let text: String = text.chars().filter(|c| !c.is_control()).collect();
+        if !text.is_empty() {
+            self.text_input.push_str(&text);
+            self.text_events.push(TextEvent::Text(text));
+        }
+    }
+
+    /// Records the text or editing key a key press types.
+    fn on_key_text(&mut self, event: &KeyEvent) {
+        if event.state != ElementState::Pressed {
+            return;
+        }
+        match &event.logical_key {
+            Key::Named(NamedKey::Backspace) => self.text_events.push(TextEvent::Backspace),
+            Key::Named(NamedKey::Delete) => self.text_events.push(TextEvent::Delete),
+            Key::Named(NamedKey::Enter) => self.text_events.push(TextEvent::Enter),
+            // Composed text arrives through `Ime::Commit` instead.
+            _ if self.ime_enabled => {}
+            _ => {
+                if let Some(text) = &event.text {
+                    self.push_text(text);
+                }
+            }
+        }
+    }
+
+    // GAMEPADS
+
+    /// The state of a connected gamepad, `None` once it disconnects
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad(&self, id: gilrs::GamepadId) -> Option<&GamepadState> {
+        self.gamepads.get(&id)
+    }
+
+    /// Connected gamepads, in no particular order
+    #[cfg(feature = "gamepad")]
+    pub fn gamepads(&self) -> impl Iterator<Item = (gilrs::GamepadId, &GamepadState)> {
+        self.gamepads.iter().map(|(&id, gamepad)| (id, gamepad))
+    }
+
+    /// Sets the fraction of each axis' range around the center that reads as zero, clamped to
+    /// `[0, 1)`
+    #[cfg(feature = "gamepad")]
+    pub fn set_gamepad_deadzone(&mut self, deadzone: f32) {
+        self.gamepad_deadzone = deadzone.clamp(0.0, 0.99);
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.deadzone = self.gamepad_deadzone;
+        }
+    }
+
+    /// Records a gamepad event, tracking gamepads as they connect and disconnect.
+    #[cfg(feature = "gamepad")]
+    pub(crate) fn on_gamepad_event(&mut self, event: &gilrs::Event) {
+        use gilrs::EventType;
+
+        if let EventType::Disconnected = event.event {
+            self.gamepads.remove(&event.id);
+            return;
+        }
+
+        // Gamepads connected before startup only show up through their first event.
+        let gamepad = self
+            .gamepads
+            .entry(event.id)
+            .or_insert_with(|| GamepadState::new(self.gamepad_deadzone));
+        match event.event {
+            EventType::ButtonPressed(button, _) if gamepad.held_buttons.insert(button) => {
+                gamepad.pressed_buttons.insert(button);
+            }
+            EventType::ButtonReleased(button, _) if gamepad.held_buttons.remove(&button) => {
+                gamepad.released_buttons.insert(button);
+            }
+            EventType::AxisChanged(axis, value, _) => {
+                gamepad.axes.insert(axis, value);
+            }
+            _ => {}
+        }
+    }
+
+    /// Records the input in `event`, if any.
+    pub(crate) fn on_window_event(&mut self, event: &WindowEvent) {
+        if let WindowEvent::KeyboardInput { event, .. } = event {
+            self.on_key_text(event);
+        }
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(last) = self.cursor_position {
+                    self.cursor_delta.0 += position.x - last.x;
+                    self.cursor_delta.1 += position.y - last.y;
+                }
+                self.cursor_position = Some(*position);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    self.held_buttons.insert(*button);
+                }
+                ElementState::Released => {
+                    self.held_buttons.remove(button);
+                }
+            },
+            WindowEvent::MouseWheel { delta, .. } => self.scroll_delta += scroll_lines(*delta),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => match state {
+                ElementState::Pressed => self.press_key(*key),
+                ElementState::Released => self.release_key(*key),
+            },
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+            WindowEvent::Ime(Ime::Enabled) => self.ime_enabled = true,
+            WindowEvent::Ime(Ime::Disabled) => self.ime_enabled = false,
+            WindowEvent::Ime(Ime::Commit(text)) => self.push_text(text),
+            // Releases aren't delivered while unfocused.
+            WindowEvent::Focused(false) => {
+                self.held_buttons.clear();
+                self.released_keys.extend(self.held_keys.drain());
+                self.modifiers = ModifiersState::empty();
+            }
+            _ => {}
+        }
+    }
+
+    /// Records `key` going down, unless it's already held, i.e. on key repeat.
+    pub(crate) fn press_key(&mut self, key: KeyCode) {
+        if self.held_keys.insert(key) {
+            self.pressed_keys.insert(key);
+        }
+    }
+
+    /// Records `key` going up, if it was held.
+    pub(crate) fn release_key(&mut self, key: KeyCode) {
+        if self.held_keys.remove(&key) {
+            self.released_keys.insert(key);
+        }
+    }
+
+    /// Records raw mouse movement from a `DeviceEvent::MouseMotion`.
+    pub(crate) fn on_mouse_motion(&mut self, (dx, dy): (f64, f64)) {
+        self.mouse_motion.0 += dx;
+        self.mouse_motion.1 += dy;
+    }
+
+    /// Resets the per-frame deltas and key edges; call once everything for the frame has read
+    /// them.
+    pub(crate) fn end_frame(&mut self) {
+        self.cursor_delta = (0.0, 0.0);
+        self.mouse_motion = (0.0, 0.0);
+        self.scroll_delta = 0.0;
+        self.pressed_keys.clear();
+        self.released_keys.clear();
+        self.text_input.clear();
+        self.text_events.clear();
+        #[cfg(feature = "gamepad")]
+        for gamepad in self.gamepads.values_mut() {
+            gamepad.pressed_buttons.clear();
+            gamepad.released_buttons.clear();
+        }
+    }
+}
+
+/// Button and axis state of one gamepad.
+#[cfg(feature = "gamepad")]
+#[derive(Debug)]
+pub struct GamepadState {
+    held_buttons: HashSet<gilrs::Button>,
+    /// Buttons that went down since the last frame
+    pressed_buttons: HashSet<gilrs::Button>,
+    /// Buttons that went up since the last frame
+    released_buttons: HashSet<gilrs::Button>,
+    /// Raw axis values in `[-1, 1]`
+    axes: HashMap<gilrs::Axis, f32>,
+    deadzone: f32,
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadState {
+    fn new(deadzone: f32) -> Self {
+        Self {
+            held_buttons: HashSet::new(),
+            pressed_buttons: HashSet::new(),
+            released_buttons: HashSet::new(),
+            axes: HashMap::new(),
+            deadzone,
+        }
+    }
+
+    pub fn is_held(&self, button: gilrs::Button) -> bool {
+        self.held_buttons.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: gilrs::Button) -> bool {
+        self.pressed_buttons.contains(&button)
+    }
+
+    pub fn just_released(&self, button: gilrs::Button) -> bool {
+        self.released_buttons.contains(&button)
+    }
+
+    /// `axis` in `[-1, 1]`, zero inside the deadzone and rescaled so it still reaches the full
+    /// range just past it. Positive is right or up for sticks.
+    pub fn axis(&self, axis: gilrs::Axis) -> f32 {
+        let value = self.axes.get(&axis).copied().unwrap_or(0.0);
+        if value.abs() <= self.deadzone {
+            return 0.0;
+        }
+        (value.signum() * (value.abs() - self.deadzone) / (1.0 - self.deadzone)).clamp(-1.0, 1.0)
+    }
+
+    /// The left stick's `(x, y)` with the deadzone applied
+    pub fn left_stick(&self) -> (f32, f32) {
+        (
+            self.axis(gilrs::Axis::LeftStickX),
+            self.axis(gilrs::Axis::LeftStickY),
+        )
+    }
+
+    /// The right stick's `(x, y)` with the deadzone applied
+    pub fn right_stick(&self) -> (f32, f32) {
+        (
+            self.axis(gilrs::Axis::RightStickX),
+            self.axis(gilrs::Axis::RightStickY),
+        )
+    }
+}
+
+/// Whether `event` lets go of held input: a key or mouse button release, or losing focus. These
+/// have to reach [`InputState`] even when the UI consumes them, or whatever was held before the
+/// UI took over would stay held.
+#[cfg(feature = "egui")]
+pub(crate) fn is_release(event: &WindowEvent) -> bool {
+    matches!(
+        event,
+        WindowEvent::KeyboardInput {
+            event: KeyEvent {
+                state: ElementState::Released,
+                ..
+            },
+            ..
+        } | WindowEvent::MouseInput {
+            state: ElementState::Released,
+            ..
+        } | WindowEvent::Focused(false)
+    )
+}
+
+/// Converts a scroll `delta` into (possibly fractional) lines.
+pub(crate) fn scroll_lines(delta: MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(position) => position.y as f32 / PIXELS_PER_LINE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_held_modifiers() {
+        let mut input = InputState::default();
+        assert!(!input.ctrl() && !input.shift() && !input.alt() && !input.logo());
+
+        input.set_modifiers(ModifiersState::CONTROL | ModifiersState::SHIFT);
+        assert!(input.ctrl());
+        assert!(input.shift());
+        assert!(!input.alt());
+        assert!(!input.logo());
+    }
+
+    #[test]
+    fn presses_last_one_frame_and_ignore_repeats() {
+        let mut input = InputState::default();
+        input.press_key(KeyCode::KeyQ);
+        assert!(input.just_pressed(KeyCode::KeyQ) && input.is_held(KeyCode::KeyQ));
+
+        input.end_frame();
+        // A repeat while held isn't a new press.
+        input.press_key(KeyCode::KeyQ);
+        assert!(!input.just_pressed(KeyCode::KeyQ) && input.is_held(KeyCode::KeyQ));
+
+        input.release_key(KeyCode::KeyQ);
+        assert!(input.just_released(KeyCode::KeyQ) && !input.is_held(KeyCode::KeyQ));
+    }
+}
@@ -1,5 +1,15 @@
+pub mod assets;
 pub mod block;
+pub mod camera;
+pub mod console;
 pub mod engine;
 pub mod graphics;
+pub mod input;
+#[cfg(feature = "record")]
+pub mod record;
 pub mod settings;
+#[cfg(feature = "egui")]
+pub mod ui;
 pub mod util;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
@@ -0,0 +1,97 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::time::Duration;
+
+use crate::util::error::RecordError;
+
+/// Pipes raw RGBA frames to an `ffmpeg` child process, which encodes them into a video whose
+/// format follows from the output's extension, e.g. `.mp4` or `.webm`.
+#[derive(Debug)]
+pub struct Recorder {
+    ffmpeg: Child,
+    stdin: ChildStdin,
+    width: u32,
+    height: u32,
+    frames_left: u32,
+    frametime: Duration,
+}
+
+impl Recorder {
+    /// Starts `ffmpeg` writing `frames` frames of `width` by `height` at `framerate` to `path`,
+    /// overwriting it. `ffmpeg` needs to be on the `PATH`.
+    pub fn start(
+        path: &Path,
+        width: u32,
+        height: u32,
+        framerate: f64,
+        frames: u32,
+    ) -> Result<Self, RecordError> {
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgba",
+            ])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &framerate.to_string()])
+            .args(["-i", "-"])
+            // Most encoders need even dimensions for 4:2:0 chroma subsampling.
+            .args([
+                "-vf",
+                "crop=trunc(iw/2)*2:trunc(ih/2)*2",
+                "-pix_fmt",
+                "yuv420p",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(RecordError::Spawn)?;
+        let stdin = ffmpeg.stdin.take().expect("ffmpeg's stdin was piped.");
+
+        Ok(Self {
+            ffmpeg,
+            stdin,
+            width,
+            height,
+            frames_left: frames,
+            frametime: Duration::from_secs_f64(1.0 / framerate),
+        })
+    }
+
+    /// Time each frame covers in the video, which the simulation should advance by per frame
+    pub fn frametime(&self) -> Duration {
+        self.frametime
+    }
+
+    /// Writes the next frame, returning whether all frames have been written. Fails if the frame
+    /// isn't the size the recording started with.
+    pub fn write_frame(&mut self, frame: &image::RgbaImage) -> Result<bool, RecordError> {
+        if frame.dimensions() != (self.width, self.height) {
+            return Err(RecordError::SizeChanged);
+        }
+
+        self.stdin.write_all(frame.as_raw())?;
+        self.frames_left = self.frames_left.saturating_sub(1);
+        Ok(self.frames_left == 0)
+    }
+
+    /// Closes the pipe and waits for `ffmpeg` to finish encoding.
+    pub fn finish(self) -> Result<(), RecordError> {
+        let Self {
+            mut ffmpeg, stdin, ..
+        } = self;
+        drop(stdin);
+
+        let status = ffmpeg.wait()?;
+        if !status.success() {
+            return Err(RecordError::Ffmpeg(status));
+        }
+        Ok(())
+    }
+}
@@ -1,21 +1,218 @@
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+use winit::dpi::{PhysicalSize, Size};
+use winit::window::WindowAttributes;
+
+use crate::util::error::SettingsError;
+
+/// Framerate paced to with [`GraphicsSettings::with_refresh_rate_pacing`] when the monitor
+/// doesn't report its refresh rate
+pub const FALLBACK_REFRESH_RATE: f64 = 60.0;
+/// Simulation updates per second unless configured otherwise
+pub const DEFAULT_UPDATE_RATE: f64 = 60.0;
+
+/// Time per frame at `framerate`, or `None` unless it's a positive, finite number of frames per
+/// second.
+pub fn frametime_at(framerate: f64) -> Option<Duration> {
+    (framerate.is_finite() && framerate > 0.0).then(|| Duration::from_secs_f64(1.0 / framerate))
+}
+
+/// Curve mapping HDR scene colors into the displayable range
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Tonemap {
+    /// `c / (1 + c)`; never clips, but desaturates and flattens highlights
+    Reinhard,
+    /// Filmic curve approximating ACES, with more contrast
+    #[default]
+    Aces,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GraphicsSettings {
+    /// Stored as the target framerate rather than a frametime, absent for vsync
+    #[serde(rename = "framerate", with = "framerate")]
     pub frametime_or_vsync: Option<Duration>,
+    /// Whether the frametime follows the refresh rate of the monitor the window is on, replacing
+    /// `frametime_or_vsync` whenever it's known
+    pub refresh_rate_pacing: bool,
+    /// Keep drawing while unfocused; updates are paused either way
     pub render_without_focus: bool,
+    /// Requested samples per pixel (1, 2, 4, 8 or 16); downgraded to what the adapter supports
+    pub msaa_samples: u32,
+    /// Whether edges are smoothed by an FXAA pass over the finished frame
+    pub fxaa: bool,
+    /// Whether the scene is drawn into a floating point target and tonemapped for display
+    pub hdr: bool,
+    /// How the HDR scene is tonemapped
+    pub tonemap: Tonemap,
+    /// Whether textures that fail to load fall back to a checkerboard instead of erroring
+    pub missing_texture_fallback: bool,
+    /// Whether to request an extended range swapchain on HDR capable displays
+    pub hdr_output: bool,
+    /// Whether frame times are recorded into `PerformanceStats`
+    pub performance_stats: bool,
+    /// Whether compute work is submitted in its own command buffer ahead of rendering
+    pub async_compute: bool,
+    /// Color the window is cleared to as soon as the surface exists, before the first real frame
+    pub splash_color: wgpu::Color,
+    /// Image drawn centered over `splash_color` on startup, if any
+    pub splash_texture: Option<PathBuf>,
+    /// If set, resizes are only applied once the size has been stable for this long
+    pub resize_debounce: Option<Duration>,
+    /// Whether to periodically log time spent acquiring frames versus rendering them
+    pub present_timing_diagnostics: bool,
+    /// Color each frame is cleared to before drawing, in linear space with an sRGB format
+    pub clear_color: wgpu::Color,
+    /// Whether to measure render pass time on the GPU, if `TIMESTAMP_QUERY` is supported
+    pub gpu_timing: bool,
+    /// Backends an adapter may be picked from
+    pub backends: wgpu::Backends,
+    /// Whether to prefer an integrated or discrete GPU
+    pub power_preference: wgpu::PowerPreference,
+    /// Fixed number of simulation updates per second, independent of the framerate
+    #[serde(deserialize_with = "deserialize_update_rate")]
+    pub update_rate: f64,
+    /// Most updates run in a single frame; time beyond that is dropped rather than caught up on
+    pub max_updates_per_frame: u32,
+    /// Directory shaders are loaded from; if relative, from the working directory or else next
+    /// to the executable
+    pub shader_dir: PathBuf,
+    /// Shaders drawn as fullscreen triangles on startup, by name within `shader_dir`
+    pub shaders: Vec<String>,
+    /// Whether frame stats are shown in the top left corner
+    pub overlay: bool,
+    /// Present mode used regardless of the framerate cap, if set; otherwise vsync is used when
+    /// uncapped
+    pub present_mode: Option<wgpu::PresentMode>,
+    /// Whether to prefer an sRGB swapchain format, so shader output is gamma encoded on present
+    pub srgb: bool,
+    /// Most frames submitted to the GPU but not yet finished before drawing waits, if set; at
+    /// least 1
+    #[serde(deserialize_with = "deserialize_frames_in_flight")]
+    pub max_frames_in_flight: Option<u32>,
+    /// Which faces scene pipelines cull, if any
+    pub cull_mode: Option<wgpu::Face>,
+    /// Winding order of front faces, for `cull_mode`
+    pub front_face: wgpu::FrontFace,
+    /// Whether GPU objects get descriptive labels, shown by graphics debuggers and in
+    /// validation errors
+    pub debug_labels: bool,
+    /// Directory wgpu traces its API calls into for offline replay, if set
+    pub api_trace_dir: Option<PathBuf>,
 }
 
 impl GraphicsSettings {
+    // PERSISTENCE
+
+    /// Loads settings from a TOML file, falling back to the defaults if it doesn't exist.
+    /// Settings missing from the file keep their defaults too.
+    pub fn load_from_path(path: &Path) -> Result<Self, SettingsError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Ok(toml::from_str(&contents)?),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save_to_path(&self, path: &Path) -> Result<(), SettingsError> {
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // BUILDERS
+
     /// Enables vsync
     pub fn with_vsync(mut self) -> Self {
         self.frametime_or_vsync = None;
+        self.refresh_rate_pacing = false;
         self
     }
 
-    /// Sets the engine to try and run at a constant frametime + disables vsync
+    /// Sets the engine to try and run at a constant frametime + disables vsync. A framerate that
+    /// isn't positive is ignored with a warning.
     pub fn with_framerate(mut self, framerate: f64) -> Self {
-        self.frametime_or_vsync = Some(Duration::from_secs_f64(1.0 / framerate));
+        let Some(frametime) = frametime_at(framerate) else {
+            log::warn!("Ignoring invalid framerate {framerate}.");
+            return self;
+        };
+        self.frametime_or_vsync = Some(frametime);
+        self.refresh_rate_pacing = false;
+        self
+    }
+
+    /// Paces frames to the refresh rate of the monitor the window is on, or
+    /// [`FALLBACK_REFRESH_RATE`] if it can't be queried, rather than a fixed framerate. Frames
+    /// are scheduled a whole frametime after the previous one instead of after the previous
+    /// frame finished, so they don't drift against the display.
+    pub fn with_refresh_rate_pacing(mut self) -> Self {
+        self.frametime_or_vsync = Some(Duration::from_secs_f64(1.0 / FALLBACK_REFRESH_RATE));
+        self.refresh_rate_pacing = true;
+        self
+    }
+
+    /// Presents with `present_mode` instead of picking vsync based on the framerate cap, e.g.
+    /// `Mailbox` for low latency vsync. Falls back to `Fifo` if the surface doesn't support it.
+    pub fn with_present_mode(mut self, present_mode: wgpu::PresentMode) -> Self {
+        self.present_mode = Some(present_mode);
+        self
+    }
+
+    /// Loads and draws `shaders` on startup instead of the built-in [`SHADERS`], each from
+    /// `<shader_dir>/<name>.wgsl`. Built-in shaders fall back to their embedded source when
+    /// they aren't on disk.
+    ///
+    /// [`SHADERS`]: crate::graphics::SHADERS
+    pub fn with_shaders(mut self, shaders: Vec<String>) -> Self {
+        self.shaders = shaders;
+        self
+    }
+
+    /// Prefers an sRGB (the default) or linear swapchain format, so colors look the same on
+    /// every platform. With sRGB, shader outputs and clear colors are in linear space and gamma
+    /// encoded when written.
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Waits for the GPU to finish older frames before drawing once `frames` are in flight.
+    ///
+    /// Fewer frames in flight means input shows up on screen sooner, at the cost of the CPU and
+    /// GPU working in lockstep rather than overlapping, which can lower the framerate. Unset by
+    /// default, leaving queueing to the driver and present mode. Has no effect on the web.
+    pub fn with_max_frames_in_flight(mut self, frames: u32) -> Self {
+        self.max_frames_in_flight = Some(frames.max(1));
+        self
+    }
+
+    /// Culls `Front` or `Back` faces of everything drawn by shaders, or nothing (the default) if
+    /// `None`. Note the built-in triangle in `shader.wgsl` is wound clockwise, so it disappears
+    /// with `Back` culling unless the front face is `Cw`.
+    pub fn with_cull_mode(mut self, cull_mode: Option<wgpu::Face>) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    /// Sets which winding order faces the camera, counter-clockwise (`Ccw`) by default
+    pub fn with_front_face(mut self, front_face: wgpu::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    /// Labels the device, pipelines (after their shaders), encoders and passes, e.g. for
+    /// RenderDoc captures
+    pub fn with_debug_labels(mut self, debug_labels: bool) -> Self {
+        self.debug_labels = debug_labels;
+        self
+    }
+
+    /// Traces every wgpu call into `dir`, replayable with wgpu's `player`. wgpu 22 has tracing
+    /// temporarily disabled and logs an error instead, so this takes effect once it's back.
+    pub fn with_api_trace_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.api_trace_dir = Some(dir.into());
         self
     }
 
@@ -23,13 +220,372 @@ impl GraphicsSettings {
         self.render_without_focus = render_without_focus;
         self
     }
+
+    /// Sets the color each frame is cleared to
+    pub fn with_clear_color(mut self, r: f64, g: f64, b: f64, a: f64) -> Self {
+        self.clear_color = wgpu::Color { r, g, b, a };
+        self
+    }
+
+    /// Sets the MSAA sample count (1, 2, 4, 8 or 16), `1` disables multisampling. If the adapter
+    /// can't multisample and resolve the surface and depth formats at that count, the highest
+    /// supported count below it is used instead (down to `1`) and a warning is logged.
+    pub fn with_msaa(mut self, samples: u32) -> Self {
+        self.msaa_samples = samples.max(1);
+        self
+    }
+
+    /// Smooths edges with a cheap FXAA post-process instead of (or on top of) MSAA, e.g. on
+    /// WebGL2 where multisampling is limited. Runs after every other pass, over the frame the
+    /// overlays are drawn onto.
+    pub fn with_fxaa(mut self, fxaa: bool) -> Self {
+        self.fxaa = fxaa;
+        self
+    }
+
+    /// Draws the scene into an `Rgba16Float` target, so colors can go beyond `1.0`, and maps it
+    /// to the swapchain with the [`Tonemap`] operator before any passes run. Falls back to
+    /// drawing straight into the swapchain if the adapter can't render to that format. Not to be
+    /// confused with `hdr_output`, which makes the swapchain itself extended range; with both,
+    /// the scene is drawn directly to it untonemapped.
+    pub fn with_hdr(mut self, hdr: bool) -> Self {
+        self.hdr = hdr;
+        self
+    }
+
+    pub fn with_tonemap(mut self, tonemap: Tonemap) -> Self {
+        self.tonemap = tonemap;
+        self
+    }
+
+    /// Disabling makes `load_texture` return errors instead of substituting a "missing texture"
+    pub fn with_missing_texture_fallback(mut self, missing_texture_fallback: bool) -> Self {
+        self.missing_texture_fallback = missing_texture_fallback;
+        self
+    }
+
+    /// Requests an `Rgba16Float` swapchain where supported, otherwise falls back to SDR
+    pub fn with_hdr_output(mut self, hdr_output: bool) -> Self {
+        self.hdr_output = hdr_output;
+        self
+    }
+
+    /// Disabling skips all frame time bookkeeping, for apps that do their own profiling
+    pub fn with_performance_stats(mut self, performance_stats: bool) -> Self {
+        self.performance_stats = performance_stats;
+        self
+    }
+
+    /// Submits compute dispatches separately before the frame's rendering.
+    ///
+    /// wgpu has a single queue per device, so work is still executed in submission order; this
+    /// only gives drivers that can overlap submissions the opportunity to do so.
+    pub fn with_async_compute(mut self, async_compute: bool) -> Self {
+        self.async_compute = async_compute;
+        self
+    }
+
+    /// Sets the color presented while the engine starts up
+    pub fn with_splash_color(mut self, r: f64, g: f64, b: f64, a: f64) -> Self {
+        self.splash_color = wgpu::Color { r, g, b, a };
+        self
+    }
+
+    /// Sets an image presented, centered and scaled to fit, while the engine starts up
+    pub fn with_splash_texture(mut self, path: impl Into<PathBuf>) -> Self {
+        self.splash_texture = Some(path.into());
+        self
+    }
+
+    /// Batches bursts of resize events, only reconfiguring once the size has been stable for
+    /// `debounce` (checked each frame); `Duration::ZERO` applies the latest size on the next frame.
+    /// Useful on compositors that emit storms of resizes while animating or tiling.
+    pub fn with_resize_debounce(mut self, debounce: Option<Duration>) -> Self {
+        self.resize_debounce = debounce;
+        self
+    }
+
+    /// Logs the average time spent waiting in `get_current_texture` separately from CPU render
+    /// time every second. A long acquire means the GPU or vsync is the bottleneck, a long CPU
+    /// time means drawing is.
+    pub fn with_present_timing_diagnostics(mut self, present_timing_diagnostics: bool) -> Self {
+        self.present_timing_diagnostics = present_timing_diagnostics;
+        self
+    }
+
+    /// Measures how long the GPU spends in the render pass with timestamp queries. Ignored with
+    /// a warning on adapters without `TIMESTAMP_QUERY`.
+    pub fn with_gpu_timing(mut self, gpu_timing: bool) -> Self {
+        self.gpu_timing = gpu_timing;
+        self
+    }
+
+    /// Restricts the adapter to the given backends, e.g. to debug a backend-specific issue
+    pub fn with_backends(mut self, backends: wgpu::Backends) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    /// E.g. `HighPerformance` to use the discrete GPU on laptops
+    pub fn with_power_preference(mut self, power_preference: wgpu::PowerPreference) -> Self {
+        self.power_preference = power_preference;
+        self
+    }
+
+    /// Sets how many fixed timestep updates run per second. A rate that isn't positive is
+    /// ignored with a warning.
+    pub fn with_update_rate(mut self, update_rate: f64) -> Self {
+        if frametime_at(update_rate).is_none() {
+            log::warn!("Ignoring invalid update rate {update_rate}.");
+            return self;
+        }
+        self.update_rate = update_rate;
+        self
+    }
+
+    /// Caps how many updates may run in one frame to catch up after a stall, 8 by default
+    pub fn with_max_updates_per_frame(mut self, max_updates_per_frame: u32) -> Self {
+        self.max_updates_per_frame = max_updates_per_frame;
+        self
+    }
+
+    pub fn with_shader_dir(mut self, shader_dir: impl Into<PathBuf>) -> Self {
+        self.shader_dir = shader_dir.into();
+        self
+    }
+
+    /// Shows the framerate, frame times and GPU in the top left corner; toggled with F3
+    pub fn with_overlay(mut self, overlay: bool) -> Self {
+        self.overlay = overlay;
+        self
+    }
 }
 
 impl Default for GraphicsSettings {
     fn default() -> Self {
         Self {
             frametime_or_vsync: None,
+            refresh_rate_pacing: false,
             render_without_focus: false,
+            msaa_samples: 1,
+            fxaa: false,
+            hdr: false,
+            tonemap: Tonemap::default(),
+            missing_texture_fallback: true,
+            hdr_output: false,
+            performance_stats: true,
+            async_compute: false,
+            splash_color: wgpu::Color::BLACK,
+            splash_texture: None,
+            resize_debounce: None,
+            present_timing_diagnostics: false,
+            clear_color: wgpu::Color::BLACK,
+            gpu_timing: false,
+            backends: wgpu::Backends::all(),
+            power_preference: Default::default(),
+            update_rate: DEFAULT_UPDATE_RATE,
+            max_updates_per_frame: 8,
+            shader_dir: "shaders".into(),
+            shaders: crate::graphics::SHADERS
+                .iter()
+                .map(|&(name, _)| name.to_owned())
+                .collect(),
+            overlay: false,
+            present_mode: None,
+            srgb: true,
+            max_frames_in_flight: None,
+            cull_mode: None,
+            front_face: wgpu::FrontFace::Ccw,
+            debug_labels: false,
+            api_trace_dir: None,
+        }
+    }
+}
+
+/// Rejects update rates that aren't positive, which would leave no time between updates.
+fn deserialize_update_rate<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<f64, D::Error> {
+    let update_rate = f64::deserialize(deserializer)?;
+    match frametime_at(update_rate) {
+        Some(_) => Ok(update_rate),
+        None => Err(serde::de::Error::custom(format!(
+            "update_rate must be a positive number, got {update_rate}"
+        ))),
+    }
+}
+
+/// Rejects `max_frames_in_flight = 0`, which would leave no frame allowed to be drawn.
+fn deserialize_frames_in_flight<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    match Option::<u32>::deserialize(deserializer)? {
+        Some(0) => Err(serde::de::Error::custom(
+            "max_frames_in_flight must be at least 1",
+        )),
+        frames => Ok(frames),
+    }
+}
+
+/// (De)serializes a frametime as the framerate it corresponds to.
+mod framerate {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        frametime: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match frametime {
+            // Round off the nanosecond truncation of the frametime, e.g. 59.99999 to 60.
+            Some(frametime) => {
+                let framerate = 1.0 / frametime.as_secs_f64();
+                serializer.serialize_some(&((framerate * 1000.0).round() / 1000.0))
+            }
+            None => serializer.serialize_none(),
         }
     }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|framerate| {
+                super::frametime_at(framerate).ok_or_else(|| {
+                    serde::de::Error::custom(format!(
+                        "framerate must be a positive number, got {framerate}"
+                    ))
+                })
+            })
+            .transpose()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct WindowSettings {
+    pub min_size: Option<Size>,
+    pub max_size: Option<Size>,
+    /// Width / height ratio the window is kept at while resizing
+    pub aspect_ratio: Option<f64>,
+}
+
+impl WindowSettings {
+    pub fn with_min_size(mut self, min_size: impl Into<Size>) -> Self {
+        self.min_size = Some(min_size.into());
+        self
+    }
+
+    pub fn with_max_size(mut self, max_size: impl Into<Size>) -> Self {
+        self.max_size = Some(max_size.into());
+        self
+    }
+
+    /// Locks the window to `width / height`; enforced by correcting the height after each resize.
+    /// Ratios that aren't finite and positive, e.g. from a zero height, are ignored with a warning.
+    pub fn with_aspect_ratio(mut self, width: f64, height: f64) -> Self {
+        match valid_aspect_ratio(width / height) {
+            Some(aspect_ratio) => self.aspect_ratio = Some(aspect_ratio),
+            None => log::warn!("Ignoring invalid aspect ratio {width} / {height}."),
+        }
+        self
+    }
+
+    /// Applies the size limits to `attributes` for window creation.
+    ///
+    /// winit has no aspect ratio constraint, and resize increments can't express one, so the
+    /// aspect ratio isn't applied here. It's only enforced after the fact, by correcting each
+    /// resize with [`Self::constrain_aspect`], so the window may briefly jitter while resizing.
+    pub fn apply(&self, mut attributes: WindowAttributes) -> WindowAttributes {
+        if let Some(min_size) = self.min_size {
+            attributes = attributes.with_min_inner_size(min_size);
+        }
+        if let Some(max_size) = self.max_size {
+            attributes = attributes.with_max_inner_size(max_size);
+        }
+        attributes
+    }
+
+    /// Returns the size `size` should be corrected to in order to keep the aspect ratio, if any.
+    pub fn constrain_aspect(&self, size: PhysicalSize<u32>) -> Option<PhysicalSize<u32>> {
+        // The field is public, so it may not have been validated.
+        let aspect_ratio = self.aspect_ratio.and_then(valid_aspect_ratio)?;
+        let height = (size.width as f64 / aspect_ratio).round() as u32;
+
+        // Allow a pixel of rounding error so we don't fight the window manager forever.
+        (height.abs_diff(size.height) > 1).then_some(PhysicalSize::new(size.width, height))
+    }
+}
+
+fn valid_aspect_ratio(aspect_ratio: f64) -> Option<f64> {
+    (aspect_ratio.is_finite() && aspect_ratio > 0.0).then_some(aspect_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_color_defaults_to_black() {
+        assert_eq!(GraphicsSettings::default().clear_color, wgpu::Color::BLACK);
+        assert_eq!(
+            GraphicsSettings::default()
+                .with_clear_color(0.1, 0.2, 0.3, 1.0)
+                .clear_color,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn constrains_the_height_to_the_aspect_ratio() {
+        let settings = WindowSettings::default().with_aspect_ratio(16.0, 9.0);
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 1000)),
+            Some(PhysicalSize::new(1600, 900))
+        );
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 900)),
+            None
+        );
+        // Within a pixel of the exact height is close enough.
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 901)),
+            None
+        );
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 899)),
+            None
+        );
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 902)),
+            Some(PhysicalSize::new(1600, 900))
+        );
+
+        assert_eq!(
+            WindowSettings::default().constrain_aspect(PhysicalSize::new(1600, 1000)),
+            None
+        );
+    }
+
+    #[test]
+    fn ignores_invalid_aspect_ratios() {
+        for (width, height) in [(16.0, 0.0), (0.0, 0.0), (0.0, 9.0), (-16.0, 9.0)] {
+            let settings = WindowSettings::default().with_aspect_ratio(width, height);
+            assert_eq!(settings.aspect_ratio, None, "{width} / {height}");
+        }
+
+        let settings = WindowSettings {
+            aspect_ratio: Some(f64::INFINITY),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.constrain_aspect(PhysicalSize::new(1600, 1000)),
+            None
+        );
+    }
 }
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::time::Duration;
+
+use winit::window::Window;
+
+use crate::settings::GraphicsSettings;
+use crate::util::callback::Callback;
+
+/// Framerate the settings panel caps to when vsync is first turned off
+const DEFAULT_FRAMERATE: f64 = 60.0;
+
+/// Builds the UI for a frame from the egui context
+pub type GuiCallback = Callback<dyn FnMut(&egui::Context)>;
+
+/// Immediate-mode UI state, feeding winit input into egui and drawing its output on top of a frame.
+pub struct Ui {
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    /// Output of the last `run`, drawn by the next `render`
+    frame: Option<UiFrame>,
+}
+
+struct UiFrame {
+    primitives: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen: egui_wgpu::ScreenDescriptor,
+}
+
+impl fmt::Debug for Ui {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ui").finish_non_exhaustive()
+    }
+}
+
+impl Ui {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context,
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        // The UI is drawn after the MSAA resolve, so it is always single-sampled.
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+
+        Self {
+            state,
+            renderer,
+            frame: None,
+        }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        self.state.egui_ctx()
+    }
+
+    /// Feeds a window event to egui, returning whether egui consumed it.
+    pub fn on_window_event(&mut self, window: &Window, event: &winit::event::WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
+    }
+
+    /// Whether egui is currently using the mouse, e.g. hovering or dragging a window.
+    pub fn wants_pointer_input(&self) -> bool {
+        self.context().wants_pointer_input()
+    }
+
+    /// Whether egui is currently using the keyboard, e.g. a focused text field.
+    pub fn wants_keyboard_input(&self) -> bool {
+        self.context().wants_keyboard_input()
+    }
+
+    /// Runs one UI frame, calling `build` to lay out the UI; the result is drawn by `render`.
+    pub fn run(&mut self, window: &Window, build: impl FnMut(&egui::Context)) {
+        let input = self.state.take_egui_input(window);
+        let output = self.state.egui_ctx().run(input, build);
+        self.state
+            .handle_platform_output(window, output.platform_output);
+
+        let pixels_per_point = output.pixels_per_point;
+        let size = window.inner_size();
+        self.frame = Some(UiFrame {
+            primitives: self.context().tessellate(output.shapes, pixels_per_point),
+            textures_delta: output.textures_delta,
+            screen: egui_wgpu::ScreenDescriptor {
+                size_in_pixels: [size.width.max(1), size.height.max(1)],
+                pixels_per_point,
+            },
+        });
+    }
+
+    /// Draws the output of the last `run` over `view`.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let Some(frame) = self.frame.take() else {
+            return;
+        };
+
+        for (id, delta) in &frame.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let commands =
+            self.renderer
+                .update_buffers(device, queue, encoder, &frame.primitives, &frame.screen);
+        queue.submit(commands);
+
+        {
+            let rp = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.renderer
+                .render(&mut rp.forget_lifetime(), &frame.primitives, &frame.screen);
+        }
+
+        for id in &frame.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+/// Shows a window for tweaking the runtime-changeable `settings`.
+pub fn settings_panel(ctx: &egui::Context, settings: &mut GraphicsSettings) {
+    egui::Window::new("Settings").show(ctx, |ui| {
+        let color = &mut settings.clear_color;
+        let mut rgba = [color.r, color.g, color.b, color.a].map(|c| c as f32);
+        ui.horizontal(|ui| {
+            ui.label("Clear color");
+            if ui.color_edit_button_rgba_unmultiplied(&mut rgba).changed() {
+                let [r, g, b, a] = rgba.map(f64::from);
+                *color = wgpu::Color { r, g, b, a };
+            }
+        });
+
+        let mut vsync = settings.frametime_or_vsync.is_none();
+        if ui.checkbox(&mut vsync, "Vsync").changed() {
+            settings.frametime_or_vsync =
+                (!vsync).then(|| Duration::from_secs_f64(1.0 / DEFAULT_FRAMERATE));
+        }
+        if let Some(frametime) = &mut settings.frametime_or_vsync {
+            let mut framerate = (1.0 / frametime.as_secs_f64()).round();
+            let slider = egui::Slider::new(&mut framerate, 10.0..=360.0).text("Framerate cap");
+            if ui.add(slider).changed() {
+                *frametime = Duration::from_secs_f64(1.0 / framerate);
+            }
+        }
+    });
+}
@@ -0,0 +1,31 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+/// A boxed user callback, opaque in `Debug` output so it can live on `Debug` structs.
+pub struct Callback<F: ?Sized>(Box<F>);
+
+impl<F: ?Sized> Callback<F> {
+    pub fn new(callback: Box<F>) -> Self {
+        Self(callback)
+    }
+}
+
+impl<F: ?Sized> Deref for Callback<F> {
+    type Target = F;
+
+    fn deref(&self) -> &F {
+        &self.0
+    }
+}
+
+impl<F: ?Sized> DerefMut for Callback<F> {
+    fn deref_mut(&mut self) -> &mut F {
+        &mut self.0
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Callback<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
@@ -1,4 +1,171 @@
+// Root
+
+/// Everything that can end `main` early
+#[derive(Debug)]
+pub enum RootError {
+    EventLoop(winit::error::EventLoopError),
+    Os(winit::error::OsError),
+}
+
+impl From<winit::error::EventLoopError> for RootError {
+    fn from(error: winit::error::EventLoopError) -> Self {
+        Self::EventLoop(error)
+    }
+}
+
+impl From<winit::error::OsError> for RootError {
+    fn from(error: winit::error::OsError) -> Self {
+        Self::Os(error)
+    }
+}
+
 // Engine
 
 #[derive(Debug)]
-pub enum DrawError {}
+pub enum DrawError {
+    /// The surface was lost or no longer matches the window; recoverable by reconfiguring it
+    SurfaceLost,
+    /// Acquiring a frame took too long; the frame can just be skipped
+    Timeout,
+    /// The GPU ran out of memory acquiring a frame; unrecoverable
+    OutOfMemory,
+}
+
+impl From<wgpu::SurfaceError> for DrawError {
+    fn from(error: wgpu::SurfaceError) -> Self {
+        match error {
+            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => Self::SurfaceLost,
+            wgpu::SurfaceError::Timeout => Self::Timeout,
+            wgpu::SurfaceError::OutOfMemory => Self::OutOfMemory,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum EngineError {
+    CreateSurfaceError(wgpu::CreateSurfaceError),
+    /// No adapter is available, or compatible with the window's surface if there is one
+    NoCompatibleAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl From<wgpu::CreateSurfaceError> for EngineError {
+    fn from(error: wgpu::CreateSurfaceError) -> Self {
+        Self::CreateSurfaceError(error)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for EngineError {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        Self::RequestDevice(error)
+    }
+}
+
+// Graphics
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(error: image::ImageError) -> Self {
+        Self::Image(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum ReadbackError {
+    /// Multisampled textures can't be copied, only resolved
+    Multisampled,
+    Map(wgpu::BufferAsyncError),
+    /// The device was dropped before the buffer finished mapping
+    Disconnected,
+    /// Only headless contexts render into a texture that can be read back
+    NotHeadless,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    /// Only 8-bit RGBA and BGRA frames can be captured
+    UnsupportedFormat(wgpu::TextureFormat),
+    Readback(ReadbackError),
+}
+
+impl From<ReadbackError> for CaptureError {
+    fn from(error: ReadbackError) -> Self {
+        Self::Readback(error)
+    }
+}
+
+#[derive(Debug)]
+pub enum ShaderError {
+    Io(std::io::Error),
+    /// Parsing or validating the shader or its pipeline failed
+    Compilation(String),
+}
+
+impl From<std::io::Error> for ShaderError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+// Recording
+
+#[cfg(feature = "record")]
+#[derive(Debug)]
+pub enum RecordError {
+    /// `ffmpeg` couldn't be started, e.g. because it isn't installed
+    Spawn(std::io::Error),
+    /// Writing to `ffmpeg` or waiting for it failed
+    Io(std::io::Error),
+    /// `ffmpeg` exited unsuccessfully
+    Ffmpeg(std::process::ExitStatus),
+    Capture(CaptureError),
+    /// The window was resized mid-recording
+    SizeChanged,
+    /// Recording was started before the graphics context existed
+    NoGraphicsContext,
+}
+
+#[cfg(feature = "record")]
+impl From<std::io::Error> for RecordError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+#[cfg(feature = "record")]
+impl From<CaptureError> for RecordError {
+    fn from(error: CaptureError) -> Self {
+        Self::Capture(error)
+    }
+}
+
+// Settings
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Deserialize(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<toml::de::Error> for SettingsError {
+    fn from(error: toml::de::Error) -> Self {
+        Self::Deserialize(error)
+    }
+}
+
+impl From<toml::ser::Error> for SettingsError {
+    fn from(error: toml::ser::Error) -> Self {
+        Self::Serialize(error)
+    }
+}
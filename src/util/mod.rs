@@ -1,2 +1,3 @@
+pub mod callback;
 pub mod error;
 pub mod performance_stats;
@@ -1,50 +1,368 @@
-use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use std::time::Duration;
 
+use web_time::Instant;
+
+/// Default number of frames stats are computed over
 const FPS_SMA_RESOLUTION: usize = 100;
+/// Multiple of the target frame time beyond which a frame counts as missed
+const MISSED_FRAME_THRESHOLD: f64 = 1.5;
 
 #[derive(Debug)]
 pub struct PerformanceStats {
+    /// Whether frames are recorded at all; when disabled `add_frame` is a no-op
+    enabled: bool,
+    /// Number of recent frames stats are computed over
+    window: usize,
     /// Time of the last frame
     last_frame: Option<Instant>,
-    /// Durations in between frames
-    frame_durations: [Duration; FPS_SMA_RESOLUTION],
-    /// Number of recorded frames; always at `FPS_SMA_RESOLUTION` besides startup
-    frames: u32,
-    /// Total duration of last `FPS_SMA_RESOLUTION` frames
+    /// Durations in between the last `window` frames, most recent first
+    frame_durations: VecDeque<Duration>,
+    /// Total of `frame_durations`
     frame_rate_accum: Duration,
+    /// Frame time frames are expected to take, if known, for counting missed frames
+    target_frame_time: Option<Duration>,
+    /// Time spent waiting to acquire swapchain textures
+    acquire_times: RollingAverage,
+    /// Time spent recording and submitting frames, excluding acquisition
+    cpu_times: RollingAverage,
+}
+
+/// Simple moving average over the last `window` samples.
+#[derive(Debug)]
+struct RollingAverage {
+    window: usize,
+    samples: VecDeque<Duration>,
+    accum: Duration,
+}
+
+impl RollingAverage {
+    fn new(window: usize) -> Self {
+        Self {
+            window,
+            samples: VecDeque::with_capacity(window),
+            accum: Default::default(),
+        }
+    }
+
+    fn add(&mut self, sample: Duration) {
+        if self.samples.len() == self.window {
+            self.accum -= self.samples.pop_back().unwrap_or_default();
+        }
+        self.accum += sample;
+        self.samples.push_front(sample);
+    }
+
+    fn average(&self) -> Duration {
+        self.accum
+            .checked_div(self.samples.len() as u32)
+            .unwrap_or_default()
+    }
 }
 
 impl Default for PerformanceStats {
     fn default() -> Self {
+        Self::new(true)
+    }
+}
+
+impl PerformanceStats {
+    pub fn new(enabled: bool) -> Self {
         Self {
+            enabled,
+            window: FPS_SMA_RESOLUTION,
             last_frame: None,
-            frame_durations: [Default::default(); 100],
-            frames: 1,
+            frame_durations: VecDeque::with_capacity(FPS_SMA_RESOLUTION),
             frame_rate_accum: Default::default(),
+            target_frame_time: None,
+            acquire_times: RollingAverage::new(FPS_SMA_RESOLUTION),
+            cpu_times: RollingAverage::new(FPS_SMA_RESOLUTION),
         }
     }
-}
 
-impl PerformanceStats {
+    /// Computes stats over the last `window` frames instead of 100, discarding any recorded.
+    pub fn with_window(self, window: usize) -> Self {
+        let window = window.max(1);
+        Self {
+            window,
+            frame_durations: VecDeque::with_capacity(window),
+            acquire_times: RollingAverage::new(window),
+            cpu_times: RollingAverage::new(window),
+            ..Self::new(self.enabled)
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Sets the frame time frames are expected to take, e.g. the framerate cap, or `None` if
+    /// unknown, e.g. under vsync.
+    pub fn set_target_frame_time(&mut self, target: Option<Duration>) {
+        self.target_frame_time = target;
+    }
+
+    pub fn target_frame_time(&self) -> Option<Duration> {
+        self.target_frame_time
+    }
+
     pub fn add_frame(&mut self, time: Instant) {
-        match self.last_frame {
-            Some(last_frame) => {
-                let duration = time - last_frame;
-                self.last_frame = Some(time);
-
-                // Save frame time, subtract oldest, and add newest to accum.
-                self.frame_durations.rotate_right(1);
-                self.frame_rate_accum -= self.frame_durations[0];
-                self.frame_rate_accum += duration;
-                self.frame_durations[0] = duration;
-
-                self.frames = (self.frames + 1).min(FPS_SMA_RESOLUTION as u32);
+        if !self.enabled {
+            return;
+        }
+
+        if let Some(last_frame) = self.last_frame {
+            let duration = time - last_frame;
+
+            // Save frame time, subtract oldest, and add newest to accum.
+            if self.frame_durations.len() == self.window {
+                self.frame_rate_accum -= self.frame_durations.pop_back().unwrap_or_default();
             }
-            None => self.last_frame = Some(time),
+            self.frame_rate_accum += duration;
+            self.frame_durations.push_front(duration);
         }
+        self.last_frame = Some(time);
+    }
+
+    /// Records how long a frame waited to acquire its swapchain texture and spent on the CPU.
+    pub fn add_frame_timings(&mut self, acquire: Duration, cpu: Duration) {
+        if !self.enabled {
+            return;
+        }
+
+        self.acquire_times.add(acquire);
+        self.cpu_times.add(cpu);
+    }
+
+    /// Average time spent waiting on presentation; if high, frames are GPU- or vsync-bound
+    pub fn get_acquire_time(&self) -> Option<Duration> {
+        self.enabled.then(|| self.acquire_times.average())
     }
 
-    pub fn get_frame_time(&self) -> Duration {
-        self.frame_rate_accum / self.frames
+    /// Average time spent rendering on the CPU; if high, drawing itself is slow
+    pub fn get_cpu_time(&self) -> Option<Duration> {
+        self.enabled.then(|| self.cpu_times.average())
+    }
+
+    /// Average frame time, or `None` if stats are disabled
+    pub fn get_frame_time(&self) -> Option<Duration> {
+        self.enabled.then(|| {
+            self.frame_rate_accum
+                .checked_div(self.frame_durations.len() as u32)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Recorded frame durations, most recent first
+    fn recorded_frames(&self) -> Option<&VecDeque<Duration>> {
+        (self.enabled && !self.frame_durations.is_empty()).then_some(&self.frame_durations)
+    }
+
+    /// Frame time below which `pct` percent (`0..=100`) of recent frames fall, linearly
+    /// interpolated between samples, or `None` if stats are disabled or nothing was recorded yet
+    pub fn percentile(&self, pct: f64) -> Option<Duration> {
+        let mut sorted: Vec<_> = self.recorded_frames()?.iter().copied().collect();
+        sorted.sort_unstable();
+
+        let rank = (pct.clamp(0.0, 100.0) / 100.0) * (sorted.len() - 1) as f64;
+        let (lower, upper) = (sorted[rank.floor() as usize], sorted[rank.ceil() as usize]);
+        Some(lower + (upper - lower).mul_f64(rank.fract()))
+    }
+
+    /// Recent frames that took over 1.5x the target frame time, or `None` if stats are disabled
+    /// or there's no target
+    pub fn missed_frames(&self) -> Option<usize> {
+        let threshold = self.target_frame_time?.mul_f64(MISSED_FRAME_THRESHOLD);
+        self.enabled.then(|| {
+            self.frame_durations
+                .iter()
+                .filter(|&&duration| duration > threshold)
+                .count()
+        })
+    }
+
+    /// Fraction (`0..=1`) of recent frames that were missed, see [`Self::missed_frames`], or
+    /// `None` if stats are disabled, there's no target or nothing was recorded yet
+    pub fn missed_frame_ratio(&self) -> Option<f64> {
+        let frames = self.recorded_frames()?.len();
+        Some(self.missed_frames()? as f64 / frames as f64)
+    }
+
+    /// Number of frames stats are currently computed over, at most the window size
+    pub fn recorded_frame_count(&self) -> usize {
+        self.frame_durations.len()
+    }
+
+    /// Shortest recent frame time, or `None` if stats are disabled or nothing was recorded yet
+    pub fn min_frame_time(&self) -> Option<Duration> {
+        self.recorded_frames()?.iter().min().copied()
+    }
+
+    /// Longest recent frame time, or `None` if stats are disabled or nothing was recorded yet
+    pub fn max_frame_time(&self) -> Option<Duration> {
+        self.recorded_frames()?.iter().max().copied()
+    }
+
+    /// Variance of recent frame times in seconds squared, higher meaning more stutter, or `None`
+    /// if stats are disabled or nothing was recorded yet
+    pub fn variance(&self) -> Option<f64> {
+        let frames = self.recorded_frames()?;
+        let mean = self.frame_rate_accum.as_secs_f64() / frames.len() as f64;
+        let squares: f64 = frames
+            .iter()
+            .map(|frame| (frame.as_secs_f64() - mean).powi(2))
+            .sum();
+        Some(squares / frames.len() as f64)
+    }
+
+    /// Standard deviation of recent frame times, or `None` if stats are disabled or nothing was
+    /// recorded yet
+    pub fn std_dev(&self) -> Option<Duration> {
+        self.variance()
+            .map(|variance| Duration::from_secs_f64(variance.sqrt()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Records frames `durations` apart, so the stats hold one sample per duration.
+    fn record(stats: &mut PerformanceStats, durations: &[Duration]) {
+        let mut time = Instant::now();
+        stats.add_frame(time);
+        for &duration in durations {
+            time += duration;
+            stats.add_frame(time);
+        }
+    }
+
+    #[test]
+    fn averages_only_recorded_frames() {
+        let mut stats = PerformanceStats::default();
+        record(&mut stats, &[ms(10), ms(20), ms(30)]);
+
+        assert_eq!(stats.recorded_frame_count(), 3);
+        assert_eq!(stats.get_frame_time(), Some(ms(20)));
+    }
+
+    #[test]
+    fn evicts_oldest_frames_at_capacity() {
+        let mut stats = PerformanceStats::default().with_window(3);
+        record(&mut stats, &[ms(100), ms(10), ms(20), ms(30)]);
+
+        assert_eq!(stats.recorded_frame_count(), 3);
+        assert_eq!(stats.get_frame_time(), Some(ms(20)));
+        assert_eq!(stats.max_frame_time(), Some(ms(30)));
+    }
+
+    #[test]
+    fn percentiles_span_recorded_frames() {
+        let mut stats = PerformanceStats::default();
+        record(&mut stats, &[ms(40), ms(10), ms(30), ms(20)]);
+
+        assert_eq!(stats.percentile(0.0), Some(ms(10)));
+        assert_eq!(stats.percentile(50.0), Some(ms(25)));
+        assert_eq!(stats.percentile(100.0), Some(ms(40)));
+    }
+
+    #[test]
+    fn empty_stats_report_nothing() {
+        let mut stats = PerformanceStats::default();
+        // A single frame has nothing to measure against yet.
+        record(&mut stats, &[]);
+
+        assert_eq!(stats.recorded_frame_count(), 0);
+        assert_eq!(stats.get_frame_time(), Some(Duration::ZERO));
+        assert_eq!(stats.percentile(50.0), None);
+        assert_eq!(stats.min_frame_time(), None);
+        assert_eq!(stats.variance(), None);
+    }
+
+    #[test]
+    fn std_dev_matches_reference() {
+        let mut stats = PerformanceStats::default();
+        // Mean 20ms with four frames 10ms off it: variance 400/6 ms^2.
+        record(
+            &mut stats,
+            &[ms(10), ms(30), ms(20), ms(10), ms(30), ms(20)],
+        );
+
+        let expected = (400.0f64 / 6.0).sqrt() * 1e-3;
+        let std_dev = stats.std_dev().unwrap().as_secs_f64();
+        assert!((std_dev - expected).abs() < 1e-9, "{std_dev} != {expected}");
+    }
+
+    #[test]
+    fn frames_on_the_threshold_are_not_missed() {
+        let mut stats = PerformanceStats::default();
+        stats.set_target_frame_time(Some(ms(10)));
+        record(&mut stats, &[ms(10), ms(15), ms(16)]);
+
+        assert_eq!(stats.missed_frames(), Some(1));
+        assert_eq!(stats.missed_frame_ratio(), Some(1.0 / 3.0));
+    }
+
+    #[test]
+    fn missed_frames_without_recorded_frames() {
+        let mut stats = PerformanceStats::default().with_window(0);
+        stats.set_target_frame_time(Some(ms(10)));
+        assert_eq!(stats.missed_frames(), Some(0));
+        assert_eq!(stats.missed_frame_ratio(), None);
+
+        // The window is clamped to a single frame.
+        record(&mut stats, &[ms(20), ms(10)]);
+        assert_eq!(stats.recorded_frame_count(), 1);
+        assert_eq!(stats.missed_frames(), Some(0));
+        assert_eq!(stats.missed_frame_ratio(), Some(0.0));
+    }
+
+    #[test]
+    fn missed_frames_need_a_target() {
+        let mut stats = PerformanceStats::default();
+        record(&mut stats, &[ms(10), ms(100)]);
+
+        assert_eq!(stats.missed_frames(), None);
+        assert_eq!(stats.missed_frame_ratio(), None);
+    }
+
+    #[test]
+    fn rolling_average_warms_up() {
+        let mut average = RollingAverage::new(4);
+        assert_eq!(average.average(), Duration::ZERO);
+
+        // Until the window fills, only the samples so far are averaged.
+        average.add(ms(10));
+        assert_eq!(average.average(), ms(10));
+        average.add(ms(20));
+        assert_eq!(average.average(), ms(15));
+    }
+
+    #[test]
+    fn rolling_average_wraps() {
+        let mut average = RollingAverage::new(2);
+        for sample in [ms(100), ms(10), ms(20), ms(30)] {
+            average.add(sample);
+        }
+
+        assert_eq!(average.samples.len(), 2);
+        assert_eq!(average.average(), ms(25));
+    }
+
+    #[test]
+    fn long_frames_are_missed() {
+        let mut stats = PerformanceStats::default();
+        stats.set_target_frame_time(Some(ms(16)));
+        record(
+            &mut stats,
+            &[ms(16), ms(17), ms(50), ms(16), ms(33), ms(15)],
+        );
+
+        assert_eq!(stats.missed_frames(), Some(2));
+        assert_eq!(stats.missed_frame_ratio(), Some(2.0 / 6.0));
     }
 }
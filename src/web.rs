@@ -0,0 +1,20 @@
+use wasm_bindgen::prelude::*;
+use winit::platform::web::EventLoopExtWebSys as _;
+use winit::window::WindowAttributes;
+
+use crate::engine::Engine;
+use crate::settings::GraphicsSettings;
+
+/// Runs the engine in a canvas appended to the page once the module is loaded.
+#[wasm_bindgen(start)]
+pub fn start() {
+    let _ = console_log::init_with_level(log::Level::Info);
+    log::info!("YAGVE v{}", env!("CARGO_PKG_VERSION"));
+
+    let event_loop = winit::event_loop::EventLoop::new().expect("Failed to create the event loop.");
+    // Returns immediately, the browser drives the event loop from here on.
+    event_loop.spawn_app(
+        Engine::new(WindowAttributes::default().with_title("YAGVX"))
+            .with_graphics_settings(GraphicsSettings::default().with_framerate(60.0)),
+    );
+}